@@ -0,0 +1,546 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use slicepdf_lib::error::{SliceError, SliceResult};
+use slicepdf_lib::services::image as image_service;
+use slicepdf_lib::services::job::Job;
+use slicepdf_lib::services::pdf::compress::{CompressMode, CompressOptions};
+use slicepdf_lib::services::pdf::optimize::{self, OptimizeOptions};
+use slicepdf_lib::services::pdf::{
+    compress, load_document, merge, protect, rotate, split, write_output,
+};
+use slicepdf_lib::services::pdf::{
+    Encryption, MergeOptions, MergePageInstruction, Passwords, PdfPermissions, PrintPermission,
+    ProtectOptions, RotatePageInstructions, SplitMode,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+#[derive(Parser)]
+#[command(
+    name = "slicepdf-cli",
+    version,
+    about = "Run the slicePDF tools from the command line"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Tool,
+}
+
+#[derive(Subcommand)]
+enum Tool {
+    /// Merge every page of the given PDFs, in order, into one file
+    Merge {
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Password for an encrypted input as FILE=PASSWORD (repeatable)
+        #[arg(short, long = "password", value_parser = parse_file_password)]
+        passwords: Vec<(PathBuf, String)>,
+        /// Add a top-level bookmark for each input, holding its own bookmarks
+        #[arg(long)]
+        bookmark_files: bool,
+    },
+    /// Rotate pages of a PDF
+    Rotate {
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Password of the input, if it is encrypted
+        #[arg(long)]
+        password: Option<String>,
+        /// Rotation for a single page as PAGE:DEGREES, e.g. 3:90 (repeatable)
+        #[arg(short, long = "page", value_parser = parse_page_rotation)]
+        pages: Vec<(u32, i32)>,
+        /// Rotation applied to every page, in degrees
+        #[arg(long)]
+        all: Option<i32>,
+    },
+    /// Recompress the images embedded in a PDF
+    Compress {
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Password of the input, if it is encrypted
+        #[arg(short, long)]
+        password: Option<String>,
+        /// Screen (72 DPI), ebook (150 DPI), print (300 DPI) or lossless
+        #[arg(long, value_enum, default_value_t = Preset::Ebook)]
+        preset: Preset,
+        /// Images shown at more pixels per inch than this are scaled down
+        #[arg(long, conflicts_with = "preset")]
+        max_dpi: Option<f32>,
+        /// JPEG quality of the recompressed images, 1 to 100
+        #[arg(long, conflicts_with = "preset")]
+        quality: Option<u8>,
+        /// Compress as little as gets the file down to this many bytes
+        #[arg(long, conflicts_with_all = ["preset", "max_dpi", "quality"])]
+        target_size: Option<u64>,
+    },
+    /// Shrink a PDF by cleaning up its structure, leaving pages and images as they are
+    Optimize {
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Password of the input, if it is encrypted
+        #[arg(short, long)]
+        password: Option<String>,
+        /// Keep objects nothing refers to
+        #[arg(long)]
+        keep_unused: bool,
+        /// Keep identical streams and fonts as separate copies
+        #[arg(long)]
+        no_dedupe: bool,
+        /// Keep page thumbnails and /PieceInfo
+        #[arg(long)]
+        keep_extras: bool,
+        /// Write a classic cross-reference table instead of object streams
+        #[arg(long)]
+        no_object_streams: bool,
+    },
+    /// Split a PDF into several files
+    Split {
+        input: PathBuf,
+        /// Folder the parts are written to
+        #[arg(short, long)]
+        out_dir: PathBuf,
+        /// One file per range, e.g. 1-3,5,8-
+        #[arg(long, group = "mode")]
+        ranges: Option<String>,
+        /// One file per N pages
+        #[arg(long, group = "mode")]
+        every: Option<u32>,
+        /// One file per page
+        #[arg(long, group = "mode")]
+        burst: bool,
+        /// One file per top-level bookmark
+        #[arg(long, group = "mode")]
+        bookmarks: bool,
+        /// Files of at most this many MB
+        #[arg(long, group = "mode", value_name = "MB")]
+        max_size: Option<f64>,
+        /// File name of each part, using {name}, {part}, {first}, {last} and {title}
+        #[arg(long, default_value = split::DEFAULT_NAME_TEMPLATE)]
+        name: String,
+        /// Password of the input, if it is encrypted
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+    /// Password-protect a PDF
+    Protect {
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Password needed to open the file, leave out to only restrict it
+        #[arg(short, long, required_unless_present = "owner_password")]
+        password: Option<String>,
+        /// Password that lifts the restrictions, defaults to --password
+        #[arg(long)]
+        owner_password: Option<String>,
+        #[arg(long, value_enum, default_value_t = Cipher::Aes256)]
+        encryption: Cipher,
+        #[command(flatten)]
+        restrictions: Restrictions,
+    },
+    /// Remove the password and restrictions from a PDF
+    Decrypt {
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Leave out for a PDF that opens without a password but is restricted
+        #[arg(short, long, default_value = "")]
+        password: String,
+    },
+    /// Convert one or more images into a single PDF
+    #[command(name = "img2pdf")]
+    ImgToPdf {
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Resize an image by exact dimensions or by percentage
+    Resize {
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        #[arg(long)]
+        width: Option<u32>,
+        #[arg(long)]
+        height: Option<u32>,
+        #[arg(long, conflicts_with_all = ["width", "height"])]
+        percent: Option<f32>,
+    },
+    /// Compress an image
+    CompressImage {
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t = ImageMode::Lossless)]
+        mode: ImageMode,
+        /// Target size in bytes, required for lossy mode
+        #[arg(long, required_if_eq("mode", "lossy"))]
+        target_size: Option<usize>,
+    },
+}
+
+#[derive(Args)]
+struct Restrictions {
+    #[arg(long, value_enum, default_value_t = Print::High)]
+    print: Print,
+    #[arg(long)]
+    no_modify: bool,
+    #[arg(long)]
+    no_copy: bool,
+    #[arg(long)]
+    no_annotate: bool,
+    #[arg(long)]
+    no_fill_forms: bool,
+    #[arg(long)]
+    no_assemble: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Print {
+    None,
+    Low,
+    High,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Cipher {
+    Aes256,
+    Aes128,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Preset {
+    Screen,
+    Ebook,
+    Print,
+    Lossless,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ImageMode {
+    Lossy,
+    Lossless,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("slicepdf-cli: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(tool: Tool) -> SliceResult<()> {
+    match tool {
+        Tool::Merge {
+            inputs,
+            output,
+            passwords: file_passwords,
+            bookmark_files,
+        } => {
+            let mut file_map = HashMap::new();
+            let mut passwords = Passwords::new();
+            let mut instructions = Vec::new();
+
+            for (index, input) in inputs.iter().enumerate() {
+                let id = index.to_string();
+                let password = file_passwords
+                    .iter()
+                    .find(|(file, _)| file == input)
+                    .map(|(_, password)| password.clone());
+                let page_count = page_count(input, password.as_deref())?;
+                if let Some(password) = password {
+                    passwords.insert(id.clone(), password);
+                }
+                instructions.extend((1..=page_count).map(|page| MergePageInstruction {
+                    sourcepdfid: id.clone(),
+                    source_page_number: page,
+                }));
+                file_map.insert(id, path_string(input)?);
+            }
+
+            write_output(&output, |output| {
+                let options = MergeOptions {
+                    bookmark_sources: bookmark_files,
+                    ..Default::default()
+                };
+                merge::merge_pdfs(
+                    &instructions,
+                    &file_map,
+                    &passwords,
+                    &options,
+                    output,
+                    &Job::silent(),
+                )
+            })?;
+            Ok(())
+        }
+        Tool::Rotate {
+            input,
+            output,
+            password,
+            pages,
+            all,
+        } => {
+            let filepath = path_string(&input)?;
+            let mut rotations = pages;
+            if let Some(rotation) = all {
+                let page_count = page_count(&input, password.as_deref())?;
+                rotations.extend((1..=page_count).map(|page| (page, rotation)));
+            }
+            if rotations.is_empty() {
+                return Err(SliceError::invalid(
+                    "Nothing to rotate, pass --page or --all",
+                ));
+            }
+
+            let instructions: Vec<_> = rotations
+                .into_iter()
+                .map(|(pagenumber, rotation)| RotatePageInstructions {
+                    pagenumber,
+                    rotation,
+                    filepath: filepath.clone(),
+                })
+                .collect();
+
+            write_output(&output, |output| {
+                rotate::rotate_pdf(&instructions, password.as_deref(), output, &Job::silent())
+            })?;
+            Ok(())
+        }
+        Tool::Compress {
+            input,
+            output,
+            password,
+            preset,
+            max_dpi,
+            quality,
+            target_size,
+        } => {
+            let mode = if let Some(bytes) = target_size {
+                CompressMode::TargetSize { bytes }
+            } else if max_dpi.is_some() || quality.is_some() {
+                let defaults = CompressOptions::default();
+                CompressMode::Custom(CompressOptions::new(
+                    max_dpi.unwrap_or(defaults.max_dpi),
+                    quality.unwrap_or(defaults.quality),
+                ))
+            } else {
+                match preset {
+                    Preset::Screen => CompressMode::Screen,
+                    Preset::Ebook => CompressMode::Ebook,
+                    Preset::Print => CompressMode::Print,
+                    Preset::Lossless => CompressMode::Lossless,
+                }
+            };
+            let report = write_output(&output, |output| {
+                compress::compress_pdf(&input, password.as_deref(), &mode, output, &Job::silent())
+            })?;
+            if report.grew() {
+                eprintln!(
+                    "slicepdf-cli: the output is {} bytes, larger than the input at {}",
+                    report.size, report.original_size
+                );
+            }
+            if let CompressMode::TargetSize { bytes } = mode {
+                if report.size > bytes {
+                    eprintln!(
+                        "slicepdf-cli: could only get down to {} bytes, above the target of {}",
+                        report.size, bytes
+                    );
+                }
+            }
+            Ok(())
+        }
+        Tool::Optimize {
+            input,
+            output,
+            password,
+            keep_unused,
+            no_dedupe,
+            keep_extras,
+            no_object_streams,
+        } => {
+            let options = OptimizeOptions {
+                remove_unused: !keep_unused,
+                deduplicate: !no_dedupe,
+                strip_extras: !keep_extras,
+                object_streams: !no_object_streams,
+            };
+            write_output(&output, |output| {
+                optimize::optimize_pdf(
+                    &input,
+                    password.as_deref(),
+                    &options,
+                    output,
+                    &Job::silent(),
+                )
+            })?;
+            Ok(())
+        }
+        Tool::Split {
+            input,
+            out_dir,
+            ranges,
+            every,
+            burst,
+            bookmarks,
+            max_size,
+            name,
+            password,
+        } => {
+            let mode = match (ranges, every, max_size) {
+                (Some(ranges), _, _) => SplitMode::Ranges { ranges },
+                (_, Some(pages), _) => SplitMode::Every { pages },
+                (_, _, Some(megabytes)) => SplitMode::MaxSize { megabytes },
+                _ if burst => SplitMode::Burst,
+                _ if bookmarks => SplitMode::Bookmarks,
+                _ => {
+                    return Err(SliceError::invalid(
+                        "Nothing to split by, pass --ranges, --every, --burst, --bookmarks or --max-size",
+                    ))
+                }
+            };
+            let report = split::split_pdf(
+                &input,
+                password.as_deref(),
+                &mode,
+                &out_dir,
+                &name,
+                &Job::silent(),
+            )?;
+            for part in report.parts {
+                println!("{}", part.path);
+            }
+            Ok(())
+        }
+        Tool::Protect {
+            input,
+            output,
+            password,
+            owner_password,
+            encryption,
+            restrictions,
+        } => {
+            let options = ProtectOptions {
+                user_password: password.unwrap_or_default(),
+                owner_password,
+                permissions: PdfPermissions {
+                    print: match restrictions.print {
+                        Print::None => PrintPermission::None,
+                        Print::Low => PrintPermission::LowQuality,
+                        Print::High => PrintPermission::HighQuality,
+                    },
+                    modify: !restrictions.no_modify,
+                    copy: !restrictions.no_copy,
+                    annotate: !restrictions.no_annotate,
+                    fill_forms: !restrictions.no_fill_forms,
+                    assemble: !restrictions.no_assemble,
+                },
+                encryption: match encryption {
+                    Cipher::Aes256 => Encryption::Aes256,
+                    Cipher::Aes128 => Encryption::Aes128,
+                },
+            };
+            protect::protect_file(&input, &output, &options, Some(qpdf()), &Job::silent())
+        }
+        Tool::Decrypt {
+            input,
+            output,
+            password,
+        } => protect::decrypt_file(&input, &output, &password, Some(qpdf()), &Job::silent()),
+        Tool::ImgToPdf { inputs, output } => write_output(&output, |output| {
+            image_service::images_to_pdf(&inputs, output, &Job::silent())
+        }),
+        Tool::Resize {
+            input,
+            output,
+            width,
+            height,
+            percent,
+        } => image_service::resize_image_service(
+            &path_string(&input)?,
+            &output,
+            width,
+            height,
+            percent,
+            &Job::silent(),
+        ),
+        Tool::CompressImage {
+            input,
+            output,
+            mode,
+            target_size,
+        } => {
+            let mode = match mode {
+                ImageMode::Lossy => "lossy",
+                ImageMode::Lossless => "lossless",
+            };
+            image_service::compress_image_service(
+                &path_string(&input)?,
+                &path_string(&output)?,
+                target_size,
+                mode,
+                &Job::silent(),
+            )
+        }
+    }
+}
+
+fn parse_page_rotation(value: &str) -> Result<(u32, i32), String> {
+    let (page, degrees) = value
+        .split_once(':')
+        .ok_or("expected PAGE:DEGREES, e.g. 3:90")?;
+    let page = page
+        .parse()
+        .map_err(|_| format!("invalid page number '{}'", page))?;
+    let degrees = degrees
+        .parse()
+        .map_err(|_| format!("invalid rotation '{}'", degrees))?;
+    Ok((page, degrees))
+}
+
+fn parse_file_password(value: &str) -> Result<(PathBuf, String), String> {
+    let (file, password) = value
+        .split_once('=')
+        .ok_or("expected FILE=PASSWORD, e.g. report.pdf=secret")?;
+    Ok((PathBuf::from(file), password.to_string()))
+}
+
+fn page_count(path: &Path, password: Option<&str>) -> SliceResult<u32> {
+    load_document(path, password).map(|doc| doc.get_pages().len() as u32)
+}
+
+fn path_string(path: &Path) -> SliceResult<String> {
+    path.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| SliceError::invalid(format!("Invalid path: {}", path.display())))
+}
+
+/// Fallback for files lopdf cannot handle. The bundled `qpdf` sits next to the executable,
+/// otherwise the one on `PATH` is used.
+fn qpdf() -> Command {
+    let bundled = std::env::current_exe()
+        .ok()
+        .and_then(|exe| {
+            Some(
+                exe.parent()?
+                    .join(format!("qpdf{}", std::env::consts::EXE_SUFFIX)),
+            )
+        })
+        .filter(|path| path.exists());
+
+    match bundled {
+        Some(path) => Command::new(path),
+        None => Command::new("qpdf"),
+    }
+}
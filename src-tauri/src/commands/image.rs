@@ -1,7 +1,7 @@
+use super::{picked_path, run_job, spawn_job};
+use crate::error::{SliceError, SliceResult};
 use crate::services::image as image_service;
 use crate::services::image::conversion;
-use caesium::parameters::{CSParameters, ChromaSubsampling};
-use caesium::{compress, compress_to_size};
 use std::path::{Path, PathBuf};
 use tauri_plugin_dialog::DialogExt;
 
@@ -12,39 +12,39 @@ pub async fn resize_image(
     width: Option<u32>,
     height: Option<u32>,
     percentage: Option<f32>,
-) -> Result<(), String> {
+    job_id: Option<String>,
+) -> SliceResult<()> {
     let input = Path::new(&input_path);
 
     let ext = input
         .extension()
         .and_then(|e| e.to_str())
-        .ok_or("Invalid input image")?;
+        .ok_or_else(|| SliceError::invalid("Invalid input image"))?;
 
     let stem = input
         .file_stem()
         .and_then(|s| s.to_str())
-        .ok_or("Invalid input image")?;
-
-    let save_path = app
-        .dialog()
-        .file()
-        .set_file_name(format!("{}_resized_by_slicePDF.{}", stem, ext))
-        .add_filter("Image", &[ext][..])
-        .blocking_save_file();
-
-    let output_path = match save_path {
-        Some(path) => path.into_path().map_err(|_| "Invalid path")?,
-        _none => return Err("Cancelled".into()),
-    };
-
-    tauri::async_runtime::spawn_blocking(move || {
-        image_service::resize_image_service(&input_path, &output_path, width, height, percentage)
-            .map_err(|e| e.to_string())?;
-
-        Ok::<(), String>(())
+        .ok_or_else(|| SliceError::invalid("Invalid input image"))?;
+
+    let output_path = picked_path(
+        app.dialog()
+            .file()
+            .set_file_name(format!("{}_resized_by_slicePDF.{}", stem, ext))
+            .add_filter("Image", &[ext][..])
+            .blocking_save_file(),
+    )?;
+
+    spawn_job(&app, job_id, move |job| {
+        image_service::resize_image_service(
+            &input_path,
+            &output_path,
+            width,
+            height,
+            percentage,
+            job,
+        )
     })
-    .await
-    .map_err(|e| e.to_string())??;
+    .await?;
 
     Ok(())
 }
@@ -54,53 +54,43 @@ pub async fn compress_image(
     input_path: String,
     target_size: Option<usize>,
     mode: String, // "lossy" | "lossless"
-) -> Result<String, String> {
-    let mut params = CSParameters::new();
-
-    // ---- Quality presets (safe defaults) ----
-    params.jpeg.quality = 75;
-    params.jpeg.chroma_subsampling = ChromaSubsampling::CS420;
-    params.jpeg.optimize = true;
-    params.jpeg.progressive = true;
-    params.jpeg.preserve_icc = true;
-
-    params.webp.lossless = mode == "lossless";
-    params.webp.quality = if mode == "lossy" { 90 } else { 100 };
-
+    job_id: Option<String>,
+) -> SliceResult<String> {
     let input = Path::new(&input_path);
 
     let ext = input
         .extension()
         .and_then(|e| e.to_str())
-        .ok_or("Invalid input image")?;
+        .ok_or_else(|| SliceError::invalid("Invalid input image"))?;
 
     let stem = input
         .file_stem()
         .and_then(|s| s.to_str())
-        .ok_or("Invalid input image")?;
+        .ok_or_else(|| SliceError::invalid("Invalid input image"))?;
     // ---- Ask save location ----
-    let save_path = app
-        .dialog()
-        .file()
-        .set_file_name(format!("{}_compressed_by_slicePDF.{}", stem, ext))
-        .blocking_save_file()
-        .ok_or("Cancelled")?
-        .into_path()
-        .map_err(|_| "Invalid path")?;
-
-    let output = save_path.to_str().ok_or("Invalid output path")?.to_string();
-
-    // ---- Compression ----
-    let result = match mode.as_str() {
-        "lossy" => {
-            let size = target_size.ok_or("Target size required for lossy mode")?;
-            compress_to_size(input_path.clone(), output.clone(), &mut params, size, true)
-        }
-        "lossless" => compress(input_path.clone(), output.clone(), &params),
-        _ => return Err("Invalid compression mode".to_string()),
-    };
-
-    result.map_err(|e| format!("Compression failed: {}", e))?;
+    let save_path = picked_path(
+        app.dialog()
+            .file()
+            .set_file_name(format!("{}_compressed_by_slicePDF.{}", stem, ext))
+            .blocking_save_file(),
+    )?;
+
+    let output = save_path
+        .to_str()
+        .ok_or_else(|| SliceError::invalid("Invalid output path"))?
+        .to_string();
+    let output_for_thread = output.clone();
+
+    spawn_job(&app, job_id, move |job| {
+        image_service::compress_image_service(
+            &input_path,
+            &output_for_thread,
+            target_size,
+            &mode,
+            job,
+        )
+    })
+    .await?;
 
     Ok(format!("Image compressed successfully:\n{}", output))
 }
@@ -110,68 +100,70 @@ pub async fn convert_image_to_pdf(
     app: tauri::AppHandle,
     input_path: String,
     file_name: String,
-) -> Result<String, String> {
+    job_id: Option<String>,
+) -> SliceResult<String> {
     let input = PathBuf::from(input_path);
     let base_name = Path::new(&file_name)
         .file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("output");
 
-    let save_path = app
-        .dialog()
-        .file()
-        .set_file_name(base_name)
-        .add_filter("PDF", &["pdf"][..])
-        .blocking_save_file()
-        .ok_or("Save cancelled")?
-        .into_path()
-        .map_err(|_| "Invalid save path".to_string())?;
-    let save_path_for_thread = save_path.clone();
-    tauri::async_runtime::spawn_blocking(move || {
-        conversion::img_to_pdf(&input, &save_path_for_thread).map_err(|e| e.to_string())
+    let save_path = picked_path(
+        app.dialog()
+            .file()
+            .set_file_name(base_name)
+            .add_filter("PDF", &["pdf"][..])
+            .blocking_save_file(),
+    )?;
+    let save_path = with_pdf_extension(save_path);
+    run_job(&app, job_id, save_path.clone(), move |output, job| {
+        conversion::img_to_pdf(&input, output, job)
     })
-    .await
-    .map_err(|e| e.to_string())?
-    .map(|_| format!("Success: PDF saved to {}", save_path.display()))
+    .await?;
+
+    Ok(format!("Success: PDF saved to {}", save_path.display()))
 }
 #[tauri::command]
 pub async fn convert_images_to_pdf(
     app: tauri::AppHandle,
     input_paths: Vec<String>,
     file_name: String,
-) -> Result<String, String> {
+    job_id: Option<String>,
+) -> SliceResult<String> {
     let base_name = Path::new(&file_name)
         .file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("output");
 
-    let save_path = app
-        .dialog()
-        .file()
-        .set_file_name(base_name)
-        .add_filter("PDF", &["pdf"][..])
-        .blocking_save_file()
-        .ok_or("Save cancelled")?
-        .into_path()
-        .map_err(|_| "Invalid save path".to_string())?;
+    let save_path = picked_path(
+        app.dialog()
+            .file()
+            .set_file_name(base_name)
+            .add_filter("PDF", &["pdf"][..])
+            .blocking_save_file(),
+    )?;
+    let save_path = with_pdf_extension(save_path);
 
     let paths: Vec<PathBuf> = input_paths.into_iter().map(PathBuf::from).collect();
 
     let count = paths.len();
 
-    let save_path_for_thread = save_path.clone();
-
-    tauri::async_runtime::spawn_blocking(move || {
-        conversion::images_to_pdf(&paths, &save_path_for_thread).map_err(|e| e.to_string())
-    })
-    .await
-    .map_err(|e| e.to_string())?
-    .map(|_| {
-        // 3. Use the original save_path and the stored count here
-        format!(
-            "Success: PDF created with {} images at {}",
-            count,
-            save_path.display()
-        )
+    run_job(&app, job_id, save_path.clone(), move |output, job| {
+        conversion::images_to_pdf(&paths, output, job)
     })
+    .await?;
+
+    Ok(format!(
+        "Success: PDF created with {} images at {}",
+        count,
+        save_path.display()
+    ))
+}
+
+/// `path` with a `.pdf` extension, in case the dialog let the user drop it.
+fn with_pdf_extension(mut path: PathBuf) -> PathBuf {
+    if path.extension().and_then(|e| e.to_str()) != Some("pdf") {
+        path.set_extension("pdf");
+    }
+    path
 }
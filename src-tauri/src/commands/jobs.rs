@@ -0,0 +1,52 @@
+use super::PROGRESS_EVENT;
+use crate::services::job::{CancelToken, Job};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::Emitter;
+
+/// Jobs currently running, kept in Tauri managed state so `cancel_job` can reach them.
+#[derive(Default)]
+pub struct JobManager {
+    next_id: AtomicU64,
+    running: Mutex<HashMap<String, CancelToken>>,
+}
+
+impl JobManager {
+    /// Registers a new job whose progress is forwarded as `job://progress` events.
+    /// The frontend may pick the id so it can filter events and cancel the job later.
+    pub fn start(&self, app: &tauri::AppHandle, job_id: Option<String>) -> Job {
+        let id = job_id
+            .unwrap_or_else(|| format!("job-{}", self.next_id.fetch_add(1, Ordering::Relaxed) + 1));
+        let cancel = CancelToken::default();
+        self.running
+            .lock()
+            .unwrap()
+            .insert(id.clone(), cancel.clone());
+
+        let app = app.clone();
+        Job::new(id, cancel, move |event| {
+            let _ = app.emit(PROGRESS_EVENT, event);
+        })
+    }
+
+    /// Asks the job to stop at its next checkpoint. Returns `false` if no such job is running.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        match self.running.lock().unwrap().get(job_id) {
+            Some(cancel) => {
+                cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish(&self, job_id: &str) {
+        self.running.lock().unwrap().remove(job_id);
+    }
+}
+
+#[tauri::command]
+pub fn cancel_job(jobs: tauri::State<'_, JobManager>, job_id: String) -> bool {
+    jobs.cancel(&job_id)
+}
@@ -1,2 +1,58 @@
 pub mod image;
+pub mod jobs;
 pub mod pdf;
+
+use crate::error::{SliceError, SliceResult};
+use crate::services::job::Job;
+use crate::services::pdf::write_output;
+use jobs::JobManager;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use tauri::Manager;
+use tauri_plugin_dialog::FilePath;
+
+pub const PROGRESS_EVENT: &str = "job://progress";
+
+impl From<tauri::Error> for SliceError {
+    fn from(err: tauri::Error) -> Self {
+        SliceError::internal(err.to_string())
+    }
+}
+
+/// Turns the result of a file dialog into a path, a dismissed dialog becomes `Cancelled`.
+fn picked_path(file: Option<FilePath>) -> SliceResult<PathBuf> {
+    file.ok_or(SliceError::Cancelled)?
+        .into_path()
+        .map_err(|_| SliceError::invalid("Invalid path"))
+}
+
+/// Runs `work` off the UI thread as a cancellable job.
+async fn spawn_job<T: Send + 'static>(
+    app: &tauri::AppHandle,
+    job_id: Option<String>,
+    work: impl FnOnce(&Job) -> SliceResult<T> + Send + 'static,
+) -> SliceResult<T> {
+    let jobs = app.state::<JobManager>();
+    let job = jobs.start(app, job_id);
+    let id = job.id().to_string();
+
+    let result = tauri::async_runtime::spawn_blocking(move || work(&job)).await;
+
+    jobs.finish(&id);
+    result?
+}
+
+/// Runs `work` off the UI thread as a cancellable job writing to `save_path`.
+/// The output only appears at `save_path` once the work has succeeded.
+async fn run_job<T: Send + 'static>(
+    app: &tauri::AppHandle,
+    job_id: Option<String>,
+    save_path: PathBuf,
+    work: impl FnOnce(&mut BufWriter<File>, &Job) -> SliceResult<T> + Send + 'static,
+) -> SliceResult<T> {
+    spawn_job(app, job_id, move |job| {
+        write_output(&save_path, |output| work(output, job))
+    })
+    .await
+}
@@ -1,17 +1,31 @@
+use super::{picked_path, run_job, spawn_job};
+use crate::error::{SliceError, SliceResult};
+use crate::services::pdf::compress::CompressMode;
+use crate::services::pdf::optimize::{self, OptimizeOptions};
 use crate::services::pdf::{
-    compress, merge, protect, rotate, MergeInstruction, MergePageInstruction, PdfResult,
-    RotatePageInstructions,
+    compress, merge, protect, rotate, split, Encryption, MergeOptions, MergePageInstruction,
+    PageSource, Passwords, PdfPermissions, ProtectOptions, RotatePageInstructions, SplitMode,
 };
+use serde::Serialize;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use tauri_plugin_dialog::DialogExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::Manager;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+use tauri_plugin_shell::ShellExt;
+
+#[derive(Serialize)]
+pub enum PdfResult {
+    Message { message: String },
+    TempPath { path: String },
+}
 
 #[tauri::command]
 pub async fn pick_output_folder(
     app: tauri::AppHandle,
     file_name: String,
-) -> Result<Option<String>, String> {
+) -> SliceResult<Option<String>> {
     let stem = std::path::Path::new(&file_name)
         .file_stem()
         .map(|s| s.to_string_lossy().to_string())
@@ -21,8 +35,8 @@ pub async fn pick_output_folder(
     let folder = app.dialog().file().blocking_pick_folder();
 
     if let Some(path_buf) = folder {
-        let final_path = path_buf.into_path().map_err(|e| e.to_string())?.join(stem);
-        fs::create_dir_all(&final_path).map_err(|e| e.to_string())?;
+        let final_path = picked_path(Some(path_buf))?.join(stem);
+        fs::create_dir_all(&final_path).map_err(|e| SliceError::io(&final_path, e))?;
         return Ok(Some(final_path.to_string_lossy().to_string()));
     }
 
@@ -35,10 +49,10 @@ pub async fn save_rendered_page(
     page_index: usize,
     output_dir: String,
     extension: String,
-) -> Result<(), String> {
+) -> SliceResult<()> {
     let file_path = PathBuf::from(output_dir).join(format!("page-{}.{}", page_index, extension));
 
-    fs::write(file_path, buffer).map_err(|e| e.to_string())?;
+    fs::write(&file_path, buffer).map_err(|e| SliceError::io(&file_path, e))?;
 
     Ok(())
 }
@@ -48,39 +62,212 @@ pub async fn merge_pdf(
     app: tauri::AppHandle,
     instructions: Vec<MergePageInstruction>,
     file_map: HashMap<String, String>,
-) -> Result<String, String> {
-    merge::merge_pdfs(app, instructions, file_map).await
+    passwords: Option<Passwords>,
+    options: Option<MergeOptions>,
+    job_id: Option<String>,
+) -> SliceResult<String> {
+    let passwords = passwords.unwrap_or_default();
+    let options = options.unwrap_or_default();
+    let save_path = picked_path(
+        app.dialog()
+            .file()
+            .set_file_name(merge::merged_file_name(&file_map))
+            .add_filter("PDF", &["pdf"][..])
+            .blocking_save_file(),
+    )?;
+    run_job(&app, job_id, save_path.clone(), move |output, job| {
+        merge::merge_pdfs(&instructions, &file_map, &passwords, &options, output, job)
+    })
+    .await?;
+
+    Ok(format!("PDF merged successfully at {:?}", save_path))
 }
 
 #[tauri::command]
 pub async fn merge_all(
     app: tauri::AppHandle,
-    instructions: Vec<MergeInstruction>,
+    instructions: Vec<PageSource>,
     file_map: HashMap<String, String>,
-) -> Result<String, String> {
-    merge::merge_all(app, instructions, file_map)
-        .await
-        .map(|_| "Merged Successfully".to_string())
-        .map_err(|e| e.to_string())
+    passwords: Option<Passwords>,
+    options: Option<MergeOptions>,
+    job_id: Option<String>,
+) -> SliceResult<String> {
+    let passwords = passwords.unwrap_or_default();
+    let options = options.unwrap_or_default();
+    let save_path = picked_path(
+        app.dialog()
+            .file()
+            .set_file_name(merge::merged_file_name(&file_map))
+            .blocking_save_file(),
+    )?;
+
+    run_job(&app, job_id, save_path, move |output, job| {
+        merge::assemble_pdf(&instructions, &file_map, &passwords, &options, output, job)
+    })
+    .await?;
+
+    Ok("Merged Successfully".to_string())
 }
 
 #[tauri::command]
-pub async fn compress_pdf(app: tauri::AppHandle, input_path: String) -> Result<String, String> {
-    compress::compress_pdf(app, input_path)
-        .await
-        .map(|_| "PDF pages compressed Successfully".to_string())
-        .map_err(|e| e.to_string())
+pub async fn compress_pdf(
+    app: tauri::AppHandle,
+    input_path: String,
+    password: Option<String>,
+    mode: Option<CompressMode>,
+    job_id: Option<String>,
+) -> SliceResult<String> {
+    let mode = mode.unwrap_or_default();
+    // Save dialog FIRST (UI thread)
+    let save_path = picked_path(
+        app.dialog()
+            .file()
+            .set_file_name("compressed_by_slice_pdf.pdf")
+            .blocking_save_file(),
+    )?;
+
+    // Heavy work off the UI thread
+    let input = PathBuf::from(&input_path);
+    let (input_password, keep_job_id) = (password.clone(), job_id.clone());
+    let report = run_job(&app, job_id, save_path.clone(), move |output, job| {
+        compress::compress_pdf(
+            Path::new(&input_path),
+            password.as_deref(),
+            &mode,
+            output,
+            job,
+        )
+    })
+    .await?;
+
+    if report.grew() {
+        let keep_original = app
+            .dialog()
+            .message(format!(
+                "The compressed PDF is {} KB, larger than the original at {} KB.",
+                report.size.div_ceil(1024),
+                report.original_size.div_ceil(1024)
+            ))
+            .title("Compression made the PDF larger")
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "Keep the original".to_string(),
+                "Keep the compressed PDF".to_string(),
+            ))
+            .blocking_show();
+        if keep_original {
+            let decrypted = run_job(&app, keep_job_id, save_path, move |output, job| {
+                compress::keep_original(&input, input_password.as_deref(), output, job)
+            })
+            .await?;
+            return Ok(if decrypted {
+                "The original PDF was kept as it was smaller, without its password"
+            } else {
+                "The original PDF was kept as it was smaller"
+            }
+            .to_string());
+        }
+    }
+
+    match mode {
+        CompressMode::TargetSize { bytes } if report.size > bytes => Ok(format!(
+            "The PDF could only be compressed to {} KB, above the target of {} KB",
+            report.size.div_ceil(1024),
+            bytes.div_ceil(1024)
+        )),
+        _ => Ok("PDF pages compressed Successfully".to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn optimize_pdf(
+    app: tauri::AppHandle,
+    input_path: String,
+    password: Option<String>,
+    options: Option<OptimizeOptions>,
+    job_id: Option<String>,
+) -> SliceResult<String> {
+    let options = options.unwrap_or_default();
+    let save_path = picked_path(
+        app.dialog()
+            .file()
+            .set_file_name("optimized_by_slice_pdf.pdf")
+            .add_filter("PDF", &["pdf"][..])
+            .blocking_save_file(),
+    )?;
+
+    run_job(&app, job_id, save_path, move |output, job| {
+        optimize::optimize_pdf(
+            Path::new(&input_path),
+            password.as_deref(),
+            &options,
+            output,
+            job,
+        )
+    })
+    .await?;
+
+    Ok("PDF optimized Successfully".to_string())
 }
 
 #[tauri::command]
 pub async fn rotate_pdf_pages(
     app: tauri::AppHandle,
     instructions: Vec<RotatePageInstructions>,
-) -> Result<String, String> {
-    rotate::rotate_pdf(app, instructions)
-        .await
-        .map(|_| "PDF pages rotated Successfully".to_string())
-        .map_err(|e| e.to_string())
+    password: Option<String>,
+    job_id: Option<String>,
+) -> SliceResult<String> {
+    if instructions.is_empty() {
+        return Err(SliceError::invalid("No instructions provided"));
+    }
+
+    let save_path = picked_path(
+        app.dialog()
+            .file()
+            .set_file_name("rotated_document_by_slice_PDF.pdf")
+            .add_filter("PDF", &["pdf"][..])
+            .blocking_save_file(),
+    )?;
+
+    run_job(&app, job_id, save_path, move |output, job| {
+        rotate::rotate_pdf(&instructions, password.as_deref(), output, job)
+    })
+    .await?;
+
+    Ok("PDF pages rotated Successfully".to_string())
+}
+
+#[tauri::command]
+pub async fn split_pdf(
+    app: tauri::AppHandle,
+    input_path: String,
+    password: Option<String>,
+    mode: SplitMode,
+    name_template: Option<String>,
+    job_id: Option<String>,
+) -> SliceResult<String> {
+    let output_dir = picked_path(app.dialog().file().blocking_pick_folder())?;
+    let name_template = name_template
+        .filter(|template| !template.trim().is_empty())
+        .unwrap_or_else(|| split::DEFAULT_NAME_TEMPLATE.to_string());
+
+    let folder = output_dir.clone();
+    let report = spawn_job(&app, job_id, move |job| {
+        split::split_pdf(
+            Path::new(&input_path),
+            password.as_deref(),
+            &mode,
+            &folder,
+            &name_template,
+            job,
+        )
+    })
+    .await?;
+
+    Ok(format!(
+        "PDF split into {} files in {:?}",
+        report.parts.len(),
+        output_dir
+    ))
 }
 
 #[tauri::command]
@@ -88,21 +275,98 @@ pub async fn protect_pdf(
     app: tauri::AppHandle,
     input_path: String,
     password: String,
-) -> Result<String, String> {
-    protect::protect_pdf(app, input_path, password)
-        .await
-        .map(|_| "PDF Encrypted Successfully".to_string())
-        .map_err(|e| e.to_string())
+    owner_password: Option<String>,
+    permissions: Option<PdfPermissions>,
+    encryption: Option<Encryption>,
+    job_id: Option<String>,
+) -> SliceResult<PdfResult> {
+    if !Path::new(&input_path).exists() {
+        return Err(SliceError::invalid(format!(
+            "Input file not found at: {}",
+            input_path
+        )));
+    }
+
+    let save_path = picked_path(
+        app.dialog()
+            .file()
+            .set_file_name("protected.pdf")
+            .add_filter("PDF", &["pdf"][..])
+            .blocking_save_file(),
+    )?;
+
+    let options = ProtectOptions {
+        user_password: password,
+        owner_password,
+        permissions: permissions.unwrap_or_default(),
+        encryption: encryption.unwrap_or_default(),
+    };
+    let qpdf = qpdf_sidecar(&app).ok();
+
+    spawn_job(&app, job_id, move |job| {
+        protect::protect_file(Path::new(&input_path), &save_path, &options, qpdf, job)
+    })
+    .await?;
+
+    Ok(PdfResult::Message {
+        message: "PDF Encrypted Successfully".into(),
+    })
 }
 
 #[tauri::command]
 pub async fn decrypt_pdf(
     app: tauri::AppHandle,
     input_path: String,
-    password: String,
+    // Empty or missing for a document that opens without a password but has restrictions.
+    password: Option<String>,
     temp: bool,
-) -> Result<PdfResult, String> {
-    protect::decrypt_pdf_service(app, input_path, password, temp)
-        .await
-        .map_err(|e| e.to_string())
+    job_id: Option<String>,
+) -> SliceResult<PdfResult> {
+    let save_path: PathBuf = if temp {
+        let mut path = app.path().temp_dir()?;
+        path.push("slice_pdf_decrypted.pdf");
+        path
+    } else {
+        picked_path(
+            app.dialog()
+                .file()
+                .set_file_name("slice_pdf_decrypted.pdf")
+                .add_filter("PDF", &["pdf"][..])
+                .blocking_save_file(),
+        )?
+    };
+
+    let qpdf = qpdf_sidecar(&app).ok();
+    let save_path_for_closure = save_path.clone();
+
+    spawn_job(&app, job_id, move |job| {
+        protect::decrypt_file(
+            Path::new(&input_path),
+            &save_path_for_closure,
+            &password.unwrap_or_default(),
+            qpdf,
+            job,
+        )
+    })
+    .await?;
+
+    if temp {
+        Ok(PdfResult::TempPath {
+            path: save_path.to_string_lossy().to_string(),
+        })
+    } else {
+        Ok(PdfResult::Message {
+            message: "PDF unlocked successfully".into(),
+        })
+    }
+}
+
+fn qpdf_sidecar(app: &tauri::AppHandle) -> SliceResult<Command> {
+    app.shell()
+        .sidecar("qpdf")
+        .map(Command::from)
+        .map_err(|e| SliceError::SidecarMissing {
+            name: "qpdf".to_string(),
+            message: e.to_string(),
+        })
 }
@@ -0,0 +1,182 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::{json, Value};
+use std::fmt;
+use std::path::Path;
+
+/// Error returned by every command and service.
+///
+/// Serialized for the frontend as `{ code, message, context }`, where `code` is one of the
+/// stable strings returned by [`SliceError::code`] and `context` holds the variant's fields.
+#[derive(Debug)]
+pub enum SliceError {
+    /// The user dismissed a dialog or aborted the job.
+    Cancelled,
+    /// The request itself is unusable (missing instructions, bad mode, unknown file id...).
+    InvalidInput {
+        message: String,
+    },
+    /// The document needs a password before it can be read.
+    Encrypted {
+        path: String,
+    },
+    /// The supplied password does not open the document.
+    WrongPassword {
+        path: String,
+    },
+    /// A page number that does not exist in the source document.
+    PageOutOfRange {
+        path: String,
+        page: u32,
+        page_count: usize,
+    },
+    Io {
+        path: String,
+        message: String,
+    },
+    /// The file exists but could not be decoded as a PDF or image.
+    ParseFailure {
+        path: String,
+        message: String,
+    },
+    /// A bundled helper binary could not be started.
+    SidecarMissing {
+        name: String,
+        message: String,
+    },
+    /// Anything else that went wrong while processing.
+    Internal {
+        message: String,
+    },
+}
+
+impl SliceError {
+    pub fn invalid(message: impl Into<String>) -> Self {
+        SliceError::InvalidInput {
+            message: message.into(),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        SliceError::Internal {
+            message: message.into(),
+        }
+    }
+
+    pub fn io(path: &Path, err: std::io::Error) -> Self {
+        SliceError::Io {
+            path: path.display().to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    pub fn parse(path: &Path, message: impl ToString) -> Self {
+        SliceError::ParseFailure {
+            path: path.display().to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Maps a `lopdf` failure on `path` to the matching variant.
+    pub fn from_lopdf(path: &Path, err: lopdf::Error) -> Self {
+        use lopdf::encryption::DecryptionError;
+
+        match err {
+            lopdf::Error::IO(e) => SliceError::io(path, e),
+            lopdf::Error::InvalidPassword
+            | lopdf::Error::Decryption(DecryptionError::IncorrectPassword) => {
+                SliceError::WrongPassword {
+                    path: path.display().to_string(),
+                }
+            }
+            other => SliceError::parse(path, other),
+        }
+    }
+
+    /// Maps an `image` failure on `path` to the matching variant.
+    pub fn from_image(path: &Path, err: image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(e) => SliceError::io(path, e),
+            other => SliceError::parse(path, other),
+        }
+    }
+
+    /// Stable identifier the frontend can match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SliceError::Cancelled => "CANCELLED",
+            SliceError::InvalidInput { .. } => "INVALID_INPUT",
+            SliceError::Encrypted { .. } => "ENCRYPTED",
+            SliceError::WrongPassword { .. } => "WRONG_PASSWORD",
+            SliceError::PageOutOfRange { .. } => "PAGE_OUT_OF_RANGE",
+            SliceError::Io { .. } => "IO",
+            SliceError::ParseFailure { .. } => "PARSE_FAILURE",
+            SliceError::SidecarMissing { .. } => "SIDECAR_MISSING",
+            SliceError::Internal { .. } => "INTERNAL",
+        }
+    }
+
+    fn context(&self) -> Value {
+        match self {
+            SliceError::Cancelled => Value::Null,
+            SliceError::InvalidInput { message } | SliceError::Internal { message } => {
+                json!({ "message": message })
+            }
+            SliceError::Encrypted { path } | SliceError::WrongPassword { path } => {
+                json!({ "path": path })
+            }
+            SliceError::PageOutOfRange {
+                path,
+                page,
+                page_count,
+            } => json!({ "path": path, "page": page, "pageCount": page_count }),
+            SliceError::Io { path, message } | SliceError::ParseFailure { path, message } => {
+                json!({ "path": path, "message": message })
+            }
+            SliceError::SidecarMissing { name, message } => {
+                json!({ "name": name, "message": message })
+            }
+        }
+    }
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SliceError::Cancelled => write!(f, "Cancelled"),
+            SliceError::InvalidInput { message } => write!(f, "{}", message),
+            SliceError::Encrypted { path } => write!(f, "{} is password protected", path),
+            SliceError::WrongPassword { path } => write!(f, "Wrong password for {}", path),
+            SliceError::PageOutOfRange {
+                path,
+                page,
+                page_count,
+            } => write!(
+                f,
+                "Page {} not found in {} (has {} pages)",
+                page, path, page_count
+            ),
+            SliceError::Io { path, message } => write!(f, "{}: {}", path, message),
+            SliceError::ParseFailure { path, message } => {
+                write!(f, "Failed to read {}: {}", path, message)
+            }
+            SliceError::SidecarMissing { name, message } => {
+                write!(f, "Sidecar '{}' could not be started: {}", name, message)
+            }
+            SliceError::Internal { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SliceError {}
+
+impl Serialize for SliceError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("SliceError", 3)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("context", &self.context())?;
+        error.end()
+    }
+}
+
+pub type SliceResult<T> = Result<T, SliceError>;
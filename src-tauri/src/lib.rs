@@ -1,4 +1,5 @@
 pub mod commands;
+pub mod error;
 pub mod services;
 
 use std::{thread, time::Duration};
@@ -11,17 +12,23 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
+        .manage(commands::jobs::JobManager::default())
         .invoke_handler(tauri::generate_handler![
             commands::pdf::save_rendered_page,
             commands::pdf::pick_output_folder,
             commands::pdf::compress_pdf,
+            commands::pdf::optimize_pdf,
             commands::pdf::merge_pdf,
             commands::pdf::merge_all,
             commands::pdf::rotate_pdf_pages,
+            commands::pdf::split_pdf,
+            commands::pdf::protect_pdf,
+            commands::pdf::decrypt_pdf,
             commands::image::convert_image_to_pdf,
             commands::image::convert_images_to_pdf,
             commands::image::compress_image,
-            commands::image::resize_image
+            commands::image::resize_image,
+            commands::jobs::cancel_job
         ])
         .setup(|app| {
             let splash = app
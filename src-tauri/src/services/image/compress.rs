@@ -1,3 +1,7 @@
+use crate::error::{SliceError, SliceResult};
+use crate::services::job::{Job, Step};
+use caesium::parameters::{CSParameters, ChromaSubsampling};
+use caesium::{compress, compress_to_size};
 use image::{GenericImageView, ImageFormat};
 use std::path::Path;
 
@@ -7,8 +11,12 @@ pub fn resize_image_service(
     width: Option<u32>,
     height: Option<u32>,
     percentage: Option<f32>,
-) -> Result<(), String> {
-    let img = image::open(input_path).map_err(|e| format!("Failed to open image: {}", e))?;
+    job: &Job,
+) -> SliceResult<()> {
+    job.report(Step::Image, 0, 1, Some(input_path.to_string()));
+    job.checkpoint()?;
+    let img =
+        image::open(input_path).map_err(|e| SliceError::from_image(Path::new(input_path), e))?;
 
     let (orig_w, orig_h) = img.dimensions();
 
@@ -39,11 +47,56 @@ pub fn resize_image_service(
         .extension()
         .and_then(|e| e.to_str())
         .and_then(ImageFormat::from_extension)
-        .ok_or("Unsupported or missing image format")?;
+        .ok_or_else(|| SliceError::invalid("Unsupported or missing image format"))?;
 
     resized
         .save_with_format(output_path, format)
-        .map_err(|e| format!("Failed to save resized image: {}", e))?;
+        .map_err(|e| SliceError::from_image(output_path, e))?;
+    job.report(Step::Image, 1, 1, None);
+
+    Ok(())
+}
+
+pub fn compress_image_service(
+    input_path: &str,
+    output_path: &str,
+    target_size: Option<usize>,
+    mode: &str, // "lossy" | "lossless"
+    job: &Job,
+) -> SliceResult<()> {
+    job.report(Step::Image, 0, 1, Some(input_path.to_string()));
+    job.checkpoint()?;
+    let mut params = CSParameters::new();
+
+    // ---- Quality presets (safe defaults) ----
+    params.jpeg.quality = 75;
+    params.jpeg.chroma_subsampling = ChromaSubsampling::CS420;
+    params.jpeg.optimize = true;
+    params.jpeg.progressive = true;
+    params.jpeg.preserve_icc = true;
+
+    params.webp.lossless = mode == "lossless";
+    params.webp.quality = if mode == "lossy" { 90 } else { 100 };
+
+    // ---- Compression ----
+    let result = match mode {
+        "lossy" => {
+            let size = target_size
+                .ok_or_else(|| SliceError::invalid("Target size required for lossy mode"))?;
+            compress_to_size(
+                input_path.to_string(),
+                output_path.to_string(),
+                &mut params,
+                size,
+                true,
+            )
+        }
+        "lossless" => compress(input_path.to_string(), output_path.to_string(), &params),
+        _ => return Err(SliceError::invalid("Invalid compression mode")),
+    };
+
+    result.map_err(|e| SliceError::internal(format!("Compression failed: {}", e)))?;
+    job.report(Step::Image, 1, 1, None);
 
     Ok(())
 }
@@ -1,39 +1,49 @@
-use pdf_converter::PdfConverter;
+use crate::error::{SliceError, SliceResult};
+use crate::services::job::Job;
+use crate::services::pdf::{
+    assemble_pdf, FitMode, ImagePageOptions, MergeOptions, Orientation, PageSource, PaperSize,
+    Passwords,
+};
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
-pub fn img_to_pdf(input_path: &Path, save_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    let converter = PdfConverter::new();
-    let output_pdf = if save_path.extension().and_then(|e| e.to_str()) == Some("pdf") {
-        save_path.to_path_buf()
-    } else {
-        save_path.with_extension("pdf")
-    };
-    converter.convert_image_to_pdf(input_path, &output_pdf)?;
-
-    if !output_pdf.exists() {
-        return Err("PDF was not created by converter".into());
-    }
-
-    Ok(())
+/// A4 portrait pages with 20 mm margins, the image fitted in between.
+const IMAGE_PAGES: ImagePageOptions = ImagePageOptions {
+    paper: PaperSize::A4,
+    orientation: Orientation::Portrait,
+    margin: 20.0 / 25.4 * 72.0,
+    fit: FitMode::Fit,
+    max_dpi: 300.0,
+};
+
+/// Writes the image at `input_path` to `output` as a one-page PDF.
+pub fn img_to_pdf<W: Write>(input_path: &Path, output: &mut W, job: &Job) -> SliceResult<()> {
+    images_to_pdf(&[input_path.to_path_buf()], output, job)
 }
 
-pub fn images_to_pdf(
+/// Writes the images at `input_paths` to `output` as a PDF with one page per image, in
+/// order. Progress is reported once per image.
+pub fn images_to_pdf<W: Write>(
     input_paths: &[PathBuf],
-    save_path: &Path,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let converter = PdfConverter::new();
-
-    // Ensure we have a .pdf extension
-    let mut output_pdf = save_path.to_path_buf();
-    if output_pdf.extension().and_then(|e| e.to_str()) != Some("pdf") {
-        output_pdf.set_extension("pdf");
+    output: &mut W,
+    job: &Job,
+) -> SliceResult<()> {
+    if input_paths.is_empty() {
+        return Err(SliceError::invalid("No images provided"));
     }
 
-    converter.convert_images_to_pdf(input_paths, &output_pdf)?;
-
-    if !output_pdf.exists() {
-        return Err("PDF was not created by converter".into());
+    let mut file_map = HashMap::new();
+    let mut pages = Vec::with_capacity(input_paths.len());
+    for (index, path) in input_paths.iter().enumerate() {
+        let file_id = index.to_string();
+        file_map.insert(file_id.clone(), path.display().to_string());
+        pages.push(PageSource::Image { file_id });
     }
-
+    let options = MergeOptions {
+        bookmark_sources: false,
+        image_pages: IMAGE_PAGES,
+    };
+    assemble_pdf(&pages, &file_map, &Passwords::new(), &options, output, job)?;
     Ok(())
 }
@@ -0,0 +1,97 @@
+use crate::error::{SliceError, SliceResult};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Step {
+    Load,
+    Page,
+    Image,
+    /// One output file of a job that writes several.
+    Part,
+    Save,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressEvent {
+    pub job_id: String,
+    pub step: Step,
+    pub done: usize,
+    pub total: usize,
+    /// File or page currently being worked on, if any.
+    pub current: Option<String>,
+}
+
+/// Shared flag a job polls to find out it should stop.
+#[derive(Clone, Default, Debug)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+type ProgressSink = Box<dyn Fn(&ProgressEvent) + Send + Sync>;
+
+/// Handle a long-running service call reports its progress through and checks for
+/// cancellation between pages and images.
+pub struct Job {
+    id: String,
+    cancel: CancelToken,
+    sink: Option<ProgressSink>,
+}
+
+impl Job {
+    pub fn new(
+        id: impl Into<String>,
+        cancel: CancelToken,
+        sink: impl Fn(&ProgressEvent) + Send + Sync + 'static,
+    ) -> Self {
+        Job {
+            id: id.into(),
+            cancel,
+            sink: Some(Box::new(sink)),
+        }
+    }
+
+    /// A job nobody is listening to and nobody can cancel.
+    pub fn silent() -> Self {
+        Job {
+            id: String::new(),
+            cancel: CancelToken::default(),
+            sink: None,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Fails with [`SliceError::Cancelled`] once the job has been cancelled.
+    pub fn checkpoint(&self) -> SliceResult<()> {
+        if self.cancel.is_cancelled() {
+            return Err(SliceError::Cancelled);
+        }
+        Ok(())
+    }
+
+    pub fn report(&self, step: Step, done: usize, total: usize, current: Option<String>) {
+        if let Some(sink) = &self.sink {
+            sink(&ProgressEvent {
+                job_id: self.id.clone(),
+                step,
+                done,
+                total,
+                current,
+            });
+        }
+    }
+}
@@ -1,67 +1,532 @@
-use jpeg_encoder::{ColorType, Encoder};
-use lopdf::{Document, Object};
-use tauri::AppHandle;
-use tauri_plugin_dialog::DialogExt;
-
-pub async fn compress_pdf(app: AppHandle, input_path: String) -> Result<(), String> {
-    // Save dialog FIRST (UI thread)
-    let save_path = app
-        .dialog()
-        .file()
-        .set_file_name("compressed_by_slice_pdf.pdf")
-        .blocking_save_file()
-        .ok_or("Save cancelled")?
-        .into_path()
-        .map_err(|_| "Invalid path")?;
-
-    // Heavy work off the UI thread
-    tauri::async_runtime::spawn_blocking(move || {
-        let mut doc = Document::load(&input_path).map_err(|e| format!("Load error: {}", e))?;
-
-        for (_, obj) in doc.objects.iter_mut() {
-            let Object::Stream(stream) = obj else {
+use super::optimize::{optimize_document, save_optimized, OptimizeOptions};
+use super::placement::image_placements;
+use super::{load_document, save_document, stream_data};
+use crate::error::{SliceError, SliceResult};
+use crate::services::job::{Job, Step};
+use image::{DynamicImage, GrayImage, RgbImage, RgbaImage};
+use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+use lopdf::{Document, Object, ObjectId, Stream};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Re-encoded images replace the original only when they are at least this fraction
+/// smaller, as the blur is not worth a few bytes.
+const MIN_SAVING: f64 = 0.1;
+
+/// Settings tried by [`CompressMode::TargetSize`], from the gentlest to the strongest.
+/// `None` leaves the images as they are.
+const TARGET_STEPS: [Option<CompressOptions>; 8] = [
+    None,
+    Some(CompressOptions::new(300.0, 85)),
+    Some(CompressOptions::new(200.0, 80)),
+    Some(CompressOptions::new(150.0, 75)),
+    Some(CompressOptions::new(120.0, 65)),
+    Some(CompressOptions::new(96.0, 55)),
+    Some(CompressOptions::new(72.0, 45)),
+    Some(CompressOptions::new(50.0, 35)),
+];
+
+/// How far images are scaled down and recompressed.
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompressOptions {
+    /// Images shown at more pixels per inch than this are scaled down to it.
+    pub max_dpi: f32,
+    /// JPEG quality of the recompressed images, 1 to 100.
+    pub quality: u8,
+}
+
+impl Default for CompressOptions {
+    fn default() -> Self {
+        CompressOptions::new(150.0, 75)
+    }
+}
+
+impl CompressOptions {
+    pub const fn new(max_dpi: f32, quality: u8) -> Self {
+        CompressOptions { max_dpi, quality }
+    }
+
+    pub(crate) fn validate(&self) -> SliceResult<()> {
+        if !self.max_dpi.is_finite() || self.max_dpi < 1.0 {
+            return Err(SliceError::invalid(format!(
+                "Maximum DPI must be at least 1, got {}",
+                self.max_dpi
+            )));
+        }
+        if !(1..=100).contains(&self.quality) {
+            return Err(SliceError::invalid(format!(
+                "Quality must be between 1 and 100, got {}",
+                self.quality
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How hard `compress_pdf` compresses.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum CompressMode {
+    /// 72 DPI, for reading on a screen.
+    Screen,
+    /// 150 DPI, for e-readers and tablets.
+    #[default]
+    Ebook,
+    /// 300 DPI, for printing.
+    Print,
+    /// Images stay as they are; only the file structure is compressed.
+    Lossless,
+    Custom(CompressOptions),
+    /// The gentlest settings that get the file down to `bytes` bytes, or the strongest
+    /// ones if none do.
+    TargetSize {
+        bytes: u64,
+    },
+}
+
+impl CompressMode {
+    /// Settings the images are compressed with, `None` when they are left alone.
+    /// Meaningless for [`CompressMode::TargetSize`], which tries several.
+    fn options(self) -> Option<CompressOptions> {
+        match self {
+            CompressMode::Screen => Some(CompressOptions::new(72.0, 60)),
+            CompressMode::Ebook => Some(CompressOptions::new(150.0, 75)),
+            CompressMode::Print => Some(CompressOptions::new(300.0, 85)),
+            CompressMode::Custom(options) => Some(options),
+            CompressMode::Lossless | CompressMode::TargetSize { .. } => None,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> SliceResult<()> {
+        match self {
+            CompressMode::Custom(options) => options.validate(),
+            CompressMode::TargetSize { bytes: 0 } => {
+                Err(SliceError::invalid("Target size must be more than 0 bytes"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct CompressReport {
+    /// Images that were recompressed, and scaled down if they had pixels to spare.
+    pub images: usize,
+    /// Images that were left as they are because recompressing them saved too little.
+    pub kept: usize,
+    /// Size of the written PDF in bytes.
+    pub size: u64,
+    /// Size of the input in bytes.
+    pub original_size: u64,
+}
+
+impl CompressReport {
+    /// Whether the written PDF ended up larger than the input.
+    pub fn grew(&self) -> bool {
+        self.size > self.original_size
+    }
+}
+
+/// An image of the document, decoded once so that it can be compressed several ways.
+struct DecodedImage {
+    id: ObjectId,
+    img: DynamicImage,
+    /// Largest size it is drawn at, in points.
+    placed: (f32, f32),
+    /// Its soft mask, which is scaled along with it.
+    mask: Option<(ObjectId, GrayImage)>,
+}
+
+/// Compresses the images of `input_path` as `mode` says and writes the result to
+/// `output`. Images the compressor cannot decode, and images that are not drawn by any
+/// page, are left as they are, and so are those that re-encoding would not make
+/// [`MIN_SAVING`] smaller. The report says if the result is larger than the input all the
+/// same, which is left for the caller to act on.
+pub fn compress_pdf<W: Write>(
+    input_path: &Path,
+    password: Option<&str>,
+    mode: &CompressMode,
+    output: &mut W,
+    job: &Job,
+) -> SliceResult<CompressReport> {
+    mode.validate()?;
+    let original_size = std::fs::metadata(input_path)
+        .map_err(|e| SliceError::io(input_path, e))?
+        .len();
+    let doc = load_document(input_path, password)?;
+    let decoded = match mode {
+        CompressMode::Lossless => Vec::new(),
+        _ => decode_images(&doc, job)?,
+    };
+
+    let (data, counts) = match *mode {
+        CompressMode::TargetSize { bytes } => fit_to_size(&doc, &decoded, bytes, job)?,
+        _ => compressed(doc, &decoded, mode.options(), job)?,
+    };
+    output
+        .write_all(&data)
+        .and_then(|_| output.flush())
+        .map_err(|e| SliceError::internal(format!("Failed to save PDF: {}", e)))?;
+
+    Ok(CompressReport {
+        images: counts.images,
+        kept: counts.kept,
+        size: data.len() as u64,
+        original_size,
+    })
+}
+
+/// Writes `input_path` to `output` as it is, for when compressing it made it larger.
+/// Encrypted inputs are saved decrypted instead, like the compressed PDF would have
+/// been. Returns whether they were.
+pub fn keep_original<W: Write>(
+    input_path: &Path,
+    password: Option<&str>,
+    output: &mut W,
+    job: &Job,
+) -> SliceResult<bool> {
+    let mut doc = load_document(input_path, password)?;
+    if doc.was_encrypted() {
+        save_document(&mut doc, output, job)?;
+        return Ok(true);
+    }
+
+    job.report(Step::Save, 0, 1, None);
+    job.checkpoint()?;
+    let mut input = File::open(input_path).map_err(|e| SliceError::io(input_path, e))?;
+    io::copy(&mut input, output)
+        .and_then(|_| output.flush())
+        .map_err(|e| SliceError::internal(format!("Failed to save PDF: {}", e)))?;
+    job.report(Step::Save, 1, 1, None);
+    Ok(false)
+}
+
+/// Saves `doc` with the settings of the first step of [`TARGET_STEPS`] that gets it down
+/// to `target` bytes, searching by halves. Falls back to the smallest attempt.
+fn fit_to_size(
+    doc: &Document,
+    decoded: &[DecodedImage],
+    target: u64,
+    job: &Job,
+) -> SliceResult<(Vec<u8>, ImageCounts)> {
+    let mut fitting = None;
+    let mut smallest: Option<(Vec<u8>, ImageCounts)> = None;
+    let (mut low, mut high) = (0, TARGET_STEPS.len());
+    while low < high {
+        let middle = (low + high) / 2;
+        let attempt = compressed(doc.clone(), decoded, TARGET_STEPS[middle], job)?;
+        if attempt.0.len() as u64 <= target {
+            fitting = Some(attempt);
+            high = middle;
+        } else {
+            if smallest
+                .as_ref()
+                .is_none_or(|best| attempt.0.len() < best.0.len())
+            {
+                smallest = Some(attempt);
+            }
+            low = middle + 1;
+        }
+    }
+    fitting
+        .or(smallest)
+        .ok_or_else(|| SliceError::internal("No compression settings were tried"))
+}
+
+/// What happened to the decoded images in one attempt.
+#[derive(Debug, Default, Clone, Copy)]
+struct ImageCounts {
+    images: usize,
+    kept: usize,
+}
+
+/// Bytes of `doc` with `decoded` recompressed as `options` say, and what became of them.
+fn compressed(
+    mut doc: Document,
+    decoded: &[DecodedImage],
+    options: Option<CompressOptions>,
+    job: &Job,
+) -> SliceResult<(Vec<u8>, ImageCounts)> {
+    let mut counts = ImageCounts::default();
+    if let Some(options) = options {
+        for image in decoded {
+            job.checkpoint()?;
+            let Some(pixels) = target_pixels(&image.img, image.placed, options.max_dpi) else {
                 continue;
             };
-
-            let data = stream.content.clone();
-            let Ok(img) = image::load_from_memory(&data) else {
+            // Images with few enough pixels already may still gain from a lower quality
+            let resized = (image.img.width(), image.img.height()) != pixels;
+            let encoded = if resized {
+                let img = image.img.resize_exact(
+                    pixels.0,
+                    pixels.1,
+                    image::imageops::FilterType::Lanczos3,
+                );
+                encode_jpeg(&img, options.quality)
+            } else {
+                encode_jpeg(&image.img, options.quality)
+            };
+            let Some(encoded) = encoded else {
+                continue;
+            };
+            let Ok(stream) = doc.get_object_mut(image.id).and_then(Object::as_stream_mut) else {
                 continue;
             };
+            if encoded.len() as f64 > stream.content.len() as f64 * (1.0 - MIN_SAVING) {
+                counts.kept += 1;
+                continue;
+            }
+            replace_pixels(stream, encoded, pixels);
+            counts.images += 1;
 
-            let resized = img.resize(
-                img.width() / 2,
-                img.height() / 2,
-                image::imageops::FilterType::Lanczos3,
-            );
-
-            let mut encoded = Vec::new();
-            let encoder = Encoder::new(&mut encoded, 65);
-
-            let rgb = resized.to_rgb8();
-            encoder
-                .encode(
-                    rgb.as_raw(),
-                    resized.width() as u16,
-                    resized.height() as u16,
-                    ColorType::Rgb,
-                )
-                .ok();
-
-            stream.set_content(encoded);
-            stream.dict.set("Filter", "DCTDecode");
-            stream.dict.set("ColorSpace", "DeviceRGB");
-            stream.dict.set("BitsPerComponent", 8);
-            stream.dict.set("Width", resized.width() as i64);
-            stream.dict.set("Height", resized.height() as i64);
+            // A mask with /Matte must have the size of its image, and any other is
+            // better off matching it too
+            if let Some((mask_id, mask)) = image.mask.as_ref().filter(|_| resized) {
+                let mask = image::imageops::resize(
+                    mask,
+                    pixels.0,
+                    pixels.1,
+                    image::imageops::FilterType::Lanczos3,
+                );
+                if let Ok(stream) = doc.get_object_mut(*mask_id).and_then(Object::as_stream_mut) {
+                    replace_mask(stream, mask);
+                }
+            }
         }
+    }
+
+    let optimize = OptimizeOptions::default();
+    optimize_document(&mut doc, &optimize);
+    doc.compress();
+    let mut data = Vec::new();
+    save_optimized(&mut doc, &optimize, &mut data, job)?;
+    Ok((data, counts))
+}
+
+/// The images of `doc` drawn by its pages that the compressor can decode.
+fn decode_images(doc: &Document, job: &Job) -> SliceResult<Vec<DecodedImage>> {
+    let placements = image_placements(doc);
+    let mut image_ids: Vec<ObjectId> = doc
+        .objects
+        .iter()
+        .filter(|(_, object)| {
+            object.as_stream().is_ok_and(|stream| {
+                stream
+                    .dict
+                    .get(b"Subtype")
+                    .and_then(Object::as_name)
+                    .is_ok_and(|subtype| subtype == b"Image")
+            })
+        })
+        .map(|(&id, _)| id)
+        .collect();
+    image_ids.sort();
 
-        doc.compress();
-        doc.save(save_path).map_err(|e| e.to_string())?;
+    let mut decoded = Vec::new();
+    let total = image_ids.len();
+    for (index, id) in image_ids.into_iter().enumerate() {
+        job.report(
+            Step::Image,
+            index,
+            total,
+            Some(format!("{} {} R", id.0, id.1)),
+        );
+        job.checkpoint()?;
+
+        let Some(&placed) = placements.get(&id) else {
+            continue;
+        };
+        let Ok(stream) = doc.get_object(id).and_then(Object::as_stream) else {
+            continue;
+        };
+        let Some(img) = decode_image(doc, stream) else {
+            continue;
+        };
+        // An image is only scaled if its soft mask can be scaled with it
+        let mask = match stream.dict.get(b"SMask").and_then(Object::as_reference) {
+            Ok(mask_id) => {
+                let Some(mask) = doc
+                    .get_object(mask_id)
+                    .and_then(Object::as_stream)
+                    .ok()
+                    .and_then(|mask| decode_samples(mask, 1))
+                else {
+                    continue;
+                };
+                Some((mask_id, mask.to_luma8()))
+            }
+            Err(_) => None,
+        };
+        decoded.push(DecodedImage {
+            id,
+            img,
+            placed,
+            mask,
+        });
+    }
+    Ok(decoded)
+}
+
+/// Size `img` should have so that, drawn `placed` points large, it has no more than
+/// `max_dpi` pixels per inch: its own size when it already has few enough. `None` when it
+/// is drawn with no size at all.
+fn target_pixels(img: &DynamicImage, placed: (f32, f32), max_dpi: f32) -> Option<(u32, u32)> {
+    let (width, height) = (img.width(), img.height());
+    // An image drawn with no size in either direction shows nothing worth measuring
+    if placed.0 <= 0.0 || placed.1 <= 0.0 {
+        return None;
+    }
+    Some((
+        ((placed.0 / 72.0 * max_dpi).round() as u32).clamp(1, width),
+        ((placed.1 / 72.0 * max_dpi).round() as u32).clamp(1, height),
+    ))
+}
 
-        Ok::<(), String>(())
+/// Pixels of an 8-bit image XObject in a gray, RGB or CMYK color space, stored either
+/// as a JPEG or as raw samples under filters lopdf can decode. CMYK pixels are held as
+/// [`DynamicImage::ImageRgba8`], one channel per ink. Images with a color key `/Mask`
+/// are left out.
+fn decode_image(doc: &Document, stream: &Stream) -> Option<DynamicImage> {
+    // Stencil masks are 1-bit and carry no color of their own
+    if stream
+        .dict
+        .get(b"ImageMask")
+        .and_then(Object::as_bool)
+        .unwrap_or(false)
+    {
+        return None;
+    }
+    // Color key masks hide the exact sample values they list, which JPEG does not keep
+    if let Ok(mask) = stream.dict.get(b"Mask") {
+        if let Ok((_, Object::Array(_))) = doc.dereference(mask) {
+            return None;
+        }
+    }
+    let components = components(doc, stream.dict.get(b"ColorSpace").ok()?)?;
+    decode_samples(stream, components)
+}
+
+/// Number of components of `color_space`, for the color spaces whose samples can be
+/// scaled and stored as a JPEG. Indexed images are left out, as scaling mixes palette
+/// indices into colors the palette doesn't have, and so are separations and DeviceN.
+fn components(doc: &Document, color_space: &Object) -> Option<u8> {
+    match doc.dereference(color_space).ok()?.1 {
+        Object::Name(name) => match name.as_slice() {
+            b"DeviceGray" => Some(1),
+            b"DeviceRGB" => Some(3),
+            b"DeviceCMYK" => Some(4),
+            _ => None,
+        },
+        // The profile is kept as it is and says how many components it has
+        Object::Array(array) => match array.as_slice() {
+            [family, profile] if family.as_name().is_ok_and(|name| name == b"ICCBased") => {
+                let profile = doc.dereference(profile).ok()?.1.as_stream().ok()?;
+                match profile.dict.get(b"N").and_then(Object::as_i64).ok()? {
+                    1 => Some(1),
+                    3 => Some(3),
+                    4 => Some(4),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Samples of an 8-bit image XObject with `components` components per pixel.
+fn decode_samples(stream: &Stream, components: u8) -> Option<DynamicImage> {
+    let dict = &stream.dict;
+    let width = u32::try_from(dict.get(b"Width").and_then(Object::as_i64).ok()?).ok()?;
+    let height = u32::try_from(dict.get(b"Height").and_then(Object::as_i64).ok()?).ok()?;
+    if dict
+        .get(b"BitsPerComponent")
+        .and_then(Object::as_i64)
+        .ok()?
+        != 8
+    {
+        return None;
+    }
+
+    let filters = if dict.has(b"Filter") {
+        stream.filters().ok()?
+    } else {
+        Vec::new()
+    };
+    if filters == [b"DCTDecode"] {
+        // The JPEG decoder turns CMYK into RGB, which would lose the inks
+        if components == 4 {
+            return None;
+        }
+        let img =
+            image::load_from_memory_with_format(&stream.content, image::ImageFormat::Jpeg).ok()?;
+        if (img.width(), img.height()) != (width, height) {
+            return None;
+        }
+        return Some(if components == 1 {
+            DynamicImage::ImageLuma8(img.to_luma8())
+        } else {
+            DynamicImage::ImageRgb8(img.to_rgb8())
+        });
+    }
+
+    let samples = stream_data(stream)?;
+    Some(match components {
+        1 => DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, samples)?),
+        3 => DynamicImage::ImageRgb8(RgbImage::from_raw(width, height, samples)?),
+        _ => DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, samples)?),
     })
-    .await
-    .map_err(|e| e.to_string())??;
+}
+
+/// `img` as a JPEG, or `None` if it is too large for one.
+fn encode_jpeg(img: &DynamicImage, quality: u8) -> Option<Vec<u8>> {
+    let width = u16::try_from(img.width()).ok()?;
+    let height = u16::try_from(img.height()).ok()?;
+    let mut encoded = Vec::new();
+    let mut encoder = Encoder::new(&mut encoded, quality);
+    match img {
+        DynamicImage::ImageLuma8(gray) => {
+            encoder.encode(gray.as_raw(), width, height, ColorType::Luma)
+        }
+        // The encoder stores CMYK inverted, the Adobe way; inverting first keeps the
+        // stored samples as they were, so the image's /Decode still applies. Inks are
+        // not subsampled, which also keeps decoders that trip over it happy
+        DynamicImage::ImageRgba8(cmyk) => {
+            encoder.set_sampling_factor(SamplingFactor::F_1_1);
+            let inverted: Vec<u8> = cmyk.as_raw().iter().map(|&ink| u8::MAX - ink).collect();
+            encoder.encode(&inverted, width, height, ColorType::Cmyk)
+        }
+        _ => encoder.encode(img.to_rgb8().as_raw(), width, height, ColorType::Rgb),
+    }
+    .ok()?;
+    Some(encoded)
+}
+
+/// Swaps the samples of `stream` for the JPEG `data` of `pixels` size, keeping its color
+/// space and decode array.
+fn replace_pixels(stream: &mut Stream, data: Vec<u8>, pixels: (u32, u32)) {
+    stream.set_content(data);
+    stream.dict.set("Filter", "DCTDecode");
+    stream.dict.remove(b"DecodeParms");
+    stream.dict.set("Width", pixels.0 as i64);
+    stream.dict.set("Height", pixels.1 as i64);
+    stream.dict.set("BitsPerComponent", 8);
+}
 
-    Ok(())
+/// Swaps the samples of the soft mask `stream` for `mask`, left unfiltered for
+/// [`Document::compress`] to deflate. Masks are not worth the artifacts of a JPEG.
+fn replace_mask(stream: &mut Stream, mask: GrayImage) {
+    let (width, height) = mask.dimensions();
+    stream.set_content(mask.into_raw());
+    stream.dict.remove(b"Filter");
+    stream.dict.remove(b"DecodeParms");
+    stream.dict.set("Width", width as i64);
+    stream.dict.set("Height", height as i64);
+    stream.dict.set("BitsPerComponent", 8);
 }
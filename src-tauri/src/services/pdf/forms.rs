@@ -0,0 +1,324 @@
+use lopdf::{decode_text_string, text_string, Dictionary, Document, Object, ObjectId};
+use std::collections::{HashMap, HashSet};
+
+use super::reachable_objects;
+
+/// Field trees are shallow in practice, anything deeper is a loop.
+const MAX_FIELD_DEPTH: usize = 32;
+
+/// Entries of a merged field and widget dictionary that belong to the widget annotation.
+const WIDGET_KEYS: [&[u8]; 23] = [
+    b"Type",
+    b"Subtype",
+    b"Rect",
+    b"Contents",
+    b"P",
+    b"NM",
+    b"M",
+    b"F",
+    b"AP",
+    b"AS",
+    b"Border",
+    b"C",
+    b"StructParent",
+    b"OC",
+    b"AF",
+    b"ca",
+    b"CA",
+    b"BM",
+    b"Lang",
+    b"H",
+    b"MK",
+    b"A",
+    b"BS",
+];
+
+/// Additional actions of a merged dictionary that the widget triggers rather than the
+/// field.
+const WIDGET_TRIGGERS: [&[u8]; 10] = [
+    b"E", b"X", b"D", b"U", b"Fo", b"Bl", b"PO", b"PC", b"PV", b"PI",
+];
+
+/// Builds the `/AcroForm` of a merged document from the widgets on its `pages`, whose
+/// fields were copied over with them. Returns `None` when no page has a form field.
+///
+/// Fields keep only the widgets that made it into the merge and fields left without any
+/// are dropped. Top-level fields are renamed with a numbered suffix when an earlier one
+/// already has their name, since fields sharing a fully-qualified name share one value.
+/// Form-wide settings and default resources come from the `/AcroForm` of `sources`.
+pub(crate) fn merged_form(
+    target: &mut Document,
+    sources: &[&Document],
+    pages: &[ObjectId],
+) -> Option<Dictionary> {
+    let mut kept = HashSet::new();
+    let mut children: HashMap<ObjectId, Vec<ObjectId>> = HashMap::new();
+    let mut fields = Vec::new();
+
+    // Walk up from each widget to its top-level field
+    for widget in pages.iter().flat_map(|&page| widget_ids(target, page)) {
+        let mut node = widget;
+        for _ in 0..MAX_FIELD_DEPTH {
+            if !kept.insert(node) {
+                break;
+            }
+            let Some(parent) = field_parent(target, node) else {
+                if is_field(target, node) {
+                    fields.push(node);
+                }
+                break;
+            };
+            children.entry(parent).or_default().push(node);
+            node = parent;
+        }
+    }
+    if fields.is_empty() {
+        return None;
+    }
+
+    for (&parent, kids) in &children {
+        prune_kids(target, parent, kids);
+    }
+    rename_duplicate_fields(target, &fields);
+
+    let mut form = Dictionary::new();
+    let mut resources = Dictionary::new();
+    let mut calculation_order = Vec::new();
+    for &source in sources {
+        let Some(source_form) = source
+            .catalog()
+            .and_then(|catalog| catalog.get(b"AcroForm"))
+            .and_then(|form| source.dereference(form))
+            .and_then(|(_, form)| form.as_dict())
+            .ok()
+        else {
+            continue;
+        };
+
+        for key in [b"DA".as_slice(), b"Q".as_slice()] {
+            if let (false, Ok(value)) = (form.has(key), source_form.get(key)) {
+                if let Ok((_, value)) = source.dereference(value) {
+                    form.set(key, value.clone());
+                }
+            }
+        }
+        if source_form
+            .get(b"NeedAppearances")
+            .and_then(Object::as_bool)
+            .unwrap_or(false)
+        {
+            form.set("NeedAppearances", true);
+        }
+        if let Ok(order) = source_form.get(b"CO").and_then(Object::as_array) {
+            calculation_order.extend(
+                order
+                    .iter()
+                    .filter(|field| field.as_reference().is_ok_and(|id| kept.contains(&id)))
+                    .cloned(),
+            );
+        }
+        if let Ok(defaults) = source_form.get(b"DR") {
+            merge_resources(target, source, defaults, &mut resources);
+        }
+    }
+
+    form.set(
+        "Fields",
+        fields
+            .into_iter()
+            .map(Object::Reference)
+            .collect::<Vec<_>>(),
+    );
+    if !resources.is_empty() {
+        form.set("DR", resources);
+    }
+    if !calculation_order.is_empty() {
+        form.set("CO", calculation_order);
+    }
+    Some(form)
+}
+
+/// Splits the widget annotation `widget_id` from its field when the two share one
+/// dictionary, so that other widgets can be added to the field. The field keeps the id,
+/// and with it every reference to it as a field, and becomes the parent of a new widget
+/// holding the annotation entries. Returns the id of that widget, `None` when
+/// `widget_id` is not such a dictionary.
+pub(crate) fn split_widget(doc: &mut Document, widget_id: ObjectId) -> Option<ObjectId> {
+    let dict = doc.get_dictionary(widget_id).ok()?;
+    let is_widget = dict
+        .get(b"Subtype")
+        .and_then(Object::as_name)
+        .is_ok_and(|subtype| subtype == b"Widget");
+    if !is_widget || !(dict.has(b"T") || dict.has(b"FT")) {
+        return None;
+    }
+
+    let mut field = dict.clone();
+    let mut widget = Dictionary::new();
+    for key in WIDGET_KEYS {
+        if let Some(value) = field.remove(key) {
+            widget.set(key, value);
+        }
+    }
+    if let Some(actions) = field.remove(b"AA") {
+        let (field_actions, widget_actions) = split_actions(doc, actions);
+        if !field_actions.is_empty() {
+            field.set("AA", field_actions);
+        }
+        if !widget_actions.is_empty() {
+            widget.set("AA", widget_actions);
+        }
+    }
+
+    widget.set("Parent", widget_id);
+    let new_id = doc.add_object(widget);
+    field.set("Kids", vec![Object::Reference(new_id)]);
+    doc.objects.insert(widget_id, Object::Dictionary(field));
+    Some(new_id)
+}
+
+/// The additional actions of a merged dictionary, sorted into the field's and the
+/// widget's.
+fn split_actions(doc: &Document, actions: Object) -> (Dictionary, Dictionary) {
+    let mut field = Dictionary::new();
+    let mut widget = Dictionary::new();
+    let Ok((_, Object::Dictionary(actions))) = doc.dereference(&actions) else {
+        return (field, widget);
+    };
+    for (trigger, action) in actions.iter() {
+        if WIDGET_TRIGGERS.contains(&trigger.as_slice()) {
+            widget.set(trigger.clone(), action.clone());
+        } else {
+            field.set(trigger.clone(), action.clone());
+        }
+    }
+    (field, widget)
+}
+
+/// Ids of the widget annotations of page `page_id`.
+fn widget_ids(doc: &Document, page_id: ObjectId) -> Vec<ObjectId> {
+    let annots = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Annots"))
+        .and_then(|annots| doc.dereference(annots))
+        .and_then(|(_, annots)| annots.as_array());
+    let Ok(annots) = annots else {
+        return Vec::new();
+    };
+    annots
+        .iter()
+        .filter_map(|annot| annot.as_reference().ok())
+        .filter(|&id| {
+            doc.get_dictionary(id)
+                .and_then(|annot| annot.get(b"Subtype"))
+                .and_then(Object::as_name)
+                .is_ok_and(|subtype| subtype == b"Widget")
+        })
+        .collect()
+}
+
+fn field_parent(doc: &Document, node: ObjectId) -> Option<ObjectId> {
+    doc.get_dictionary(node)
+        .and_then(|node| node.get(b"Parent"))
+        .and_then(Object::as_reference)
+        .ok()
+}
+
+/// Whether `node` is a field rather than a widget that belongs to no form.
+fn is_field(doc: &Document, node: ObjectId) -> bool {
+    doc.get_dictionary(node)
+        .is_ok_and(|node| node.has(b"FT") || node.has(b"T") || node.has(b"Kids"))
+}
+
+/// Sets the `/Kids` of field `parent` to `kids`, keeping their original order. Kids not
+/// listed before, such as widgets of a page merged twice, go at the end.
+fn prune_kids(doc: &mut Document, parent: ObjectId, kids: &[ObjectId]) {
+    let original: Vec<ObjectId> = doc
+        .get_dictionary(parent)
+        .and_then(|node| node.get(b"Kids"))
+        .and_then(|kids| doc.dereference(kids))
+        .and_then(|(_, kids)| kids.as_array())
+        .map(|kids| {
+            kids.iter()
+                .filter_map(|kid| kid.as_reference().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let pruned: Vec<Object> = original
+        .iter()
+        .filter(|kid| kids.contains(kid))
+        .chain(kids.iter().filter(|kid| !original.contains(kid)))
+        .map(|&kid| Object::Reference(kid))
+        .collect();
+    if let Ok(node) = doc.get_dictionary_mut(parent) {
+        node.set("Kids", pruned);
+    }
+}
+
+/// Gives every top-level field in `fields` a name no earlier one has, appending `_1`,
+/// `_2` and so on.
+fn rename_duplicate_fields(doc: &mut Document, fields: &[ObjectId]) {
+    let mut taken = HashSet::new();
+    for &field in fields {
+        let Ok(node) = doc.get_dictionary_mut(field) else {
+            continue;
+        };
+        let Some(name) = node.get(b"T").ok().and_then(|t| decode_text_string(t).ok()) else {
+            continue;
+        };
+        let mut unique = name.clone();
+        let mut number = 1;
+        while !taken.insert(unique.clone()) {
+            unique = format!("{}_{}", name, number);
+            number += 1;
+        }
+        if unique != name {
+            node.set("T", text_string(&unique));
+        }
+    }
+}
+
+/// Adds the default resources `defaults` of a source form to `merged`, keeping the
+/// first entry for each name, and copies the objects they use into `target`.
+fn merge_resources(
+    target: &mut Document,
+    source: &Document,
+    defaults: &Object,
+    merged: &mut Dictionary,
+) {
+    let Ok(defaults) = source
+        .dereference(defaults)
+        .and_then(|(_, defaults)| defaults.as_dict())
+    else {
+        return;
+    };
+
+    let mut added = Vec::new();
+    for (category, entries) in defaults.iter() {
+        let Ok(entries) = source
+            .dereference(entries)
+            .and_then(|(_, entries)| entries.as_dict())
+        else {
+            continue;
+        };
+        if !merged.has(category) {
+            merged.set(category.clone(), Dictionary::new());
+        }
+        let Ok(merged) = merged.get_mut(category).and_then(Object::as_dict_mut) else {
+            continue;
+        };
+        for (name, value) in entries.iter() {
+            if !merged.has(name) {
+                merged.set(name.clone(), value.clone());
+                added.push(value);
+            }
+        }
+    }
+
+    for id in reachable_objects(source, added, &HashSet::new()) {
+        if let Some(object) = source.objects.get(&id) {
+            target.objects.entry(id).or_insert_with(|| object.clone());
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use lopdf::{dictionary, Object, Stream};
+
+/// What the header of a JPEG file says about the image, as far as embedding it goes.
+pub(crate) struct JpegInfo {
+    pub width: u32,
+    pub height: u32,
+    /// 1 for gray, 3 for RGB (usually stored as YCbCr), 4 for CMYK.
+    pub components: u8,
+    /// Whether the file has Adobe's APP14 marker.
+    adobe: bool,
+}
+
+/// Reads the header of `data`. Returns `None` unless it is a JPEG that PDF readers decode
+/// as they are: 8-bit baseline or progressive, with 1, 3 or 4 components.
+pub(crate) fn jpeg_info(data: &[u8]) -> Option<JpegInfo> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut adobe = false;
+    let mut pos = 2;
+
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        // Markers may be padded with any number of 0xFF bytes
+        while *data.get(pos)? == 0xFF {
+            pos += 1;
+        }
+        let marker = data[pos];
+        pos += 1;
+        // Restart markers and TEM carry no length
+        if matches!(marker, 0xD0..=0xD7 | 0x01) {
+            continue;
+        }
+        let length = u16::from_be_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize;
+        let segment = data.get(pos + 2..pos + length)?;
+
+        match marker {
+            0xEE if segment.starts_with(b"Adobe") => adobe = true,
+            // Baseline, extended and progressive Huffman-coded frames
+            0xC0..=0xC2 => {
+                let &[precision, h1, h0, w1, w0, components, ..] = segment else {
+                    return None;
+                };
+                let height = u16::from_be_bytes([h1, h0]) as u32;
+                let width = u16::from_be_bytes([w1, w0]) as u32;
+                let supported =
+                    precision == 8 && width > 0 && height > 0 && matches!(components, 1 | 3 | 4);
+                return supported.then_some(JpegInfo {
+                    width,
+                    height,
+                    components,
+                    adobe,
+                });
+            }
+            // Lossless, hierarchical and arithmetic-coded frames, or image data before
+            // any frame header
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xDA => return None,
+            _ => {}
+        }
+        pos += length;
+    }
+}
+
+/// Image XObject holding the JPEG `data` as it is.
+pub(crate) fn jpeg_image(data: Vec<u8>, info: &JpegInfo) -> Stream {
+    let color_space = match info.components {
+        1 => "DeviceGray",
+        3 => "DeviceRGB",
+        _ => "DeviceCMYK",
+    };
+    let mut dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => info.width as i64,
+        "Height" => info.height as i64,
+        "ColorSpace" => color_space,
+        "BitsPerComponent" => 8,
+        "Filter" => "DCTDecode",
+    };
+    // Adobe writes CMYK JPEGs with every channel inverted
+    if info.components == 4 && info.adobe {
+        let decode: Vec<Object> = [1, 0, 1, 0, 1, 0, 1, 0]
+            .into_iter()
+            .map(Object::from)
+            .collect();
+        dict.set("Decode", decode);
+    }
+    Stream::new(dict, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::pdf::pages::{image_page, ImagePageOptions, PaperSize};
+    use lopdf::Document;
+
+    /// A 64 x 32 baseline RGB JPEG.
+    fn baseline_jpeg() -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbImage::from_pixel(64, 32, image::Rgb([30, 120, 200]))
+            .write_to(
+                &mut std::io::Cursor::new(&mut data),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        data
+    }
+
+    /// Adds a page made from the image file holding `data`, one pixel per point.
+    fn add_image_page(doc: &mut Document, data: &[u8]) -> crate::error::SliceResult<Stream> {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("photo.jpg");
+        std::fs::write(&path, data).unwrap();
+        let options = ImagePageOptions {
+            paper: PaperSize::SameAsImage,
+            max_dpi: 72.0,
+            ..ImagePageOptions::default()
+        };
+        let page_id = image_page(doc, &path, &options)?;
+        let image_id = doc
+            .get_dictionary(page_id)
+            .and_then(|page| page.get(b"Resources"))
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .and_then(|xobjects| xobjects.get(b"Im1"))
+            .and_then(Object::as_reference)
+            .unwrap();
+        Ok(doc
+            .get_object(image_id)
+            .unwrap()
+            .as_stream()
+            .unwrap()
+            .clone())
+    }
+
+    #[test]
+    fn baseline_jpegs_are_embedded_as_they_are() {
+        let data = baseline_jpeg();
+        let mut doc = Document::with_version("1.7");
+        let image = add_image_page(&mut doc, &data).unwrap();
+
+        assert_eq!(image.content, data);
+        let entry = |key: &[u8]| image.dict.get(key).unwrap().clone();
+        assert_eq!(entry(b"Filter"), Object::from("DCTDecode"));
+        assert_eq!(entry(b"Width"), Object::from(64));
+        assert_eq!(entry(b"Height"), Object::from(32));
+        assert_eq!(entry(b"ColorSpace"), Object::from("DeviceRGB"));
+        assert!(!image.dict.has(b"Decode"));
+    }
+
+    #[test]
+    fn adobe_cmyk_jpegs_are_inverted_back() {
+        // The header of a 16 x 8 CMYK JPEG written by Adobe software
+        let mut data = vec![0xFF, 0xD8];
+        data.extend([0xFF, 0xEE, 0x00, 0x0E]);
+        data.extend(b"Adobe");
+        data.extend([0x00, 0x64, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        data.extend([0xFF, 0xC0, 0x00, 0x14, 0x08, 0x00, 0x08, 0x00, 0x10, 0x04]);
+        for component in 1..=4 {
+            data.extend([component, 0x11, 0x00]);
+        }
+
+        let info = jpeg_info(&data).unwrap();
+        let image = jpeg_image(data, &info);
+
+        assert_eq!(
+            image.dict.get(b"ColorSpace").unwrap(),
+            &Object::from("DeviceCMYK")
+        );
+        let decode: Vec<Object> = [1, 0, 1, 0, 1, 0, 1, 0]
+            .into_iter()
+            .map(Object::from)
+            .collect();
+        assert_eq!(image.dict.get(b"Decode").unwrap(), &Object::Array(decode));
+    }
+
+    #[test]
+    fn jpegs_truncated_before_the_image_data_are_an_error() {
+        let data = baseline_jpeg();
+        let frame = data
+            .windows(2)
+            .position(|marker| marker == [0xFF, 0xC0])
+            .unwrap();
+        // Cut in the middle of a marker, a segment length, a segment and the frame header
+        for length in [3, 5, 20, frame + 6] {
+            let truncated = &data[..length];
+            assert!(jpeg_info(truncated).is_none());
+            let mut doc = Document::with_version("1.7");
+            assert!(add_image_page(&mut doc, truncated).is_err());
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::outline::{explicit_destination, named_destinations, raw_destination, DestinationName};
+
+/// Named destinations of a merged document, gathered from its sources.
+#[derive(Default)]
+pub(crate) struct MergedDestinations {
+    dests: BTreeMap<DestinationName, Vec<Object>>,
+}
+
+/// What to do with an annotation of a page copied into a merge.
+enum LinkUpdate {
+    Keep,
+    Remove,
+    /// Point the link at the named destination under its new name.
+    Rename(Object),
+}
+
+impl MergedDestinations {
+    /// Carries over the named destinations of `source` that lead to a page in `included`.
+    /// A name an earlier source already uses gets a numbered suffix.
+    ///
+    /// The pages of `source` in `included` must already be in `target` under the same ids.
+    /// Their links are updated to match: named destinations follow the renames, and links
+    /// to pages left out of the merge are removed.
+    pub fn add_source(
+        &mut self,
+        target: &mut Document,
+        source: &Document,
+        included: &HashSet<ObjectId>,
+    ) {
+        let mut renamed = HashMap::new();
+        for (name, dest) in named_destinations(source) {
+            let Some(dest) = explicit_destination(source, dest) else {
+                continue;
+            };
+            let page = dest.first().and_then(|page| page.as_reference().ok());
+            if !page.is_some_and(|page| included.contains(&page)) || renamed.contains_key(&name) {
+                continue;
+            }
+            let mut unique = name.clone();
+            let mut number = 1;
+            while self.dests.contains_key(&unique) {
+                unique = name.numbered(number);
+                number += 1;
+            }
+            self.dests.insert(unique.clone(), dest.to_vec());
+            renamed.insert(name, unique);
+        }
+
+        for page_id in source.get_pages().into_values() {
+            if included.contains(&page_id) {
+                update_links(target, source, page_id, included, &renamed);
+            }
+        }
+    }
+
+    /// Sets `/Dests` and `/Names` in `catalog` when there are destinations to keep.
+    pub fn write(self, catalog: &mut Dictionary) {
+        let mut by_name = Dictionary::new();
+        let mut tree = Vec::new();
+        for (name, dest) in self.dests {
+            match name {
+                DestinationName::Name(key) => by_name.set(key, dest),
+                DestinationName::String(key) => {
+                    tree.push(Object::string_literal(key));
+                    tree.push(dest.into());
+                }
+            }
+        }
+        if !by_name.is_empty() {
+            catalog.set("Dests", by_name);
+        }
+        if !tree.is_empty() {
+            // Keys come out of the map sorted, as a name tree needs them.
+            catalog.set(
+                "Names",
+                dictionary! { "Dests" => dictionary! { "Names" => tree } },
+            );
+        }
+    }
+}
+
+/// Applies [`link_update`] to every annotation of page `page_id` in `target`.
+fn update_links(
+    target: &mut Document,
+    source: &Document,
+    page_id: ObjectId,
+    included: &HashSet<ObjectId>,
+    renamed: &HashMap<DestinationName, DestinationName>,
+) {
+    let annots = source
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Annots"))
+        .and_then(|annots| source.dereference(annots))
+        .and_then(|(_, annots)| annots.as_array());
+    let Ok(annots) = annots else {
+        return;
+    };
+
+    let mut kept = Vec::with_capacity(annots.len());
+    let mut changed = false;
+    for annot in annots {
+        match link_update(source, annot, included, renamed) {
+            LinkUpdate::Keep => kept.push(annot.clone()),
+            LinkUpdate::Remove => changed = true,
+            LinkUpdate::Rename(name) => {
+                changed = true;
+                match annot {
+                    Object::Reference(id) => {
+                        if let Ok(mut link) = target.get_dictionary(*id).cloned() {
+                            set_link_destination(target, &mut link, name);
+                            target.objects.insert(*id, Object::Dictionary(link));
+                        }
+                        kept.push(annot.clone());
+                    }
+                    Object::Dictionary(link) => {
+                        let mut link = link.clone();
+                        set_link_destination(target, &mut link, name);
+                        kept.push(Object::Dictionary(link));
+                    }
+                    _ => kept.push(annot.clone()),
+                }
+            }
+        }
+    }
+
+    if changed {
+        if let Ok(page) = target.get_dictionary_mut(page_id) {
+            if kept.is_empty() {
+                page.remove(b"Annots");
+            } else {
+                page.set("Annots", kept);
+            }
+        }
+    }
+}
+
+/// Decides what happens to `annot` once its page is merged. Only links to a destination
+/// inside the source are touched; other annotations and links to URIs or other files
+/// are kept as they are.
+fn link_update(
+    source: &Document,
+    annot: &Object,
+    included: &HashSet<ObjectId>,
+    renamed: &HashMap<DestinationName, DestinationName>,
+) -> LinkUpdate {
+    let Some(link) = source
+        .dereference(annot)
+        .ok()
+        .and_then(|(_, annot)| annot.as_dict().ok())
+    else {
+        return LinkUpdate::Keep;
+    };
+    if link.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Link".as_slice()) {
+        return LinkUpdate::Keep;
+    }
+    let Some(dest) = raw_destination(source, link) else {
+        return LinkUpdate::Keep;
+    };
+
+    let named = source
+        .dereference(dest)
+        .ok()
+        .and_then(|(_, dest)| DestinationName::from_object(dest));
+    if let Some(name) = named {
+        return match renamed.get(&name) {
+            Some(unique) if *unique == name => LinkUpdate::Keep,
+            Some(unique) => LinkUpdate::Rename(unique.to_object()),
+            None => LinkUpdate::Remove,
+        };
+    }
+
+    let page = explicit_destination(source, dest)
+        .and_then(|dest| dest.first())
+        .and_then(|page| page.as_reference().ok());
+    match page {
+        Some(page) if !included.contains(&page) => LinkUpdate::Remove,
+        _ => LinkUpdate::Keep,
+    }
+}
+
+/// Points `link` at the named destination `name`, through its `/Dest` or the `/D` of its
+/// `/GoTo` action, whichever it uses.
+fn set_link_destination(target: &mut Document, link: &mut Dictionary, name: Object) {
+    if link.has(b"Dest") {
+        link.set("Dest", name);
+        return;
+    }
+    match link.get_mut(b"A") {
+        Ok(Object::Dictionary(action)) => action.set("D", name),
+        Ok(Object::Reference(id)) => {
+            if let Ok(action) = target.get_dictionary_mut(*id) {
+                action.set("D", name);
+            }
+        }
+        _ => {}
+    }
+}
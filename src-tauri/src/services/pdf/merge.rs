@@ -1,8 +1,16 @@
-use image::GenericImageView;
-use lopdf::{dictionary, Document, Object, Stream};
+use lopdf::{dictionary, Document, Object, ObjectId};
 
-use std::collections::HashMap;
-use tauri_plugin_dialog::DialogExt;
+use super::forms::{merged_form, split_widget};
+use super::links::MergedDestinations;
+use super::optimize::{optimize_document, save_optimized, OptimizeOptions};
+use super::outline::{read_outline, write_outline, Bookmark};
+use super::pages::{blank_page, image_page, text_page, ImagePageOptions, PageSize};
+use super::{load_document, page_label, reachable_objects, resolved_page, Passwords};
+use crate::error::{SliceError, SliceResult};
+use crate::services::job::{Job, Step};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
 
 #[derive(serde::Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -11,349 +19,1106 @@ pub struct MergePageInstruction {
     pub source_page_number: u32,
 }
 
-#[derive(serde::Deserialize)]
-pub struct MergeInstruction {
-    #[serde(rename = "fileId")]
-    file_id: String,
-    #[serde(rename = "pageNumber")]
-    page_number: u32,
-    kind: String,
+impl From<&MergePageInstruction> for PageSource {
+    fn from(instruction: &MergePageInstruction) -> Self {
+        PageSource::Pdf {
+            file_id: instruction.sourcepdfid.clone(),
+            page_number: instruction.source_page_number,
+            rotation: 0,
+        }
+    }
 }
 
-pub async fn merge_pdfs(
-    app: tauri::AppHandle,
-    instructions: Vec<MergePageInstruction>,
-    file_map: HashMap<String, String>,
-) -> Result<String, String> {
-    let mut parts = Vec::new();
+/// One page of an assembled document. Files are named by their id in the file map.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum PageSource {
+    /// Page `page_number` (1-based) of a PDF, turned clockwise by `rotation` degrees on
+    /// top of its own rotation.
+    Pdf {
+        file_id: String,
+        page_number: u32,
+        #[serde(default)]
+        rotation: i32,
+    },
+    /// An image file on a page of its own, laid out by [`MergeOptions::image_pages`].
+    Image { file_id: String },
+    /// An empty page.
+    Blank { size: PageSize },
+    /// A page showing `text`, such as a title between the parts of a document.
+    Text {
+        text: String,
+        #[serde(default)]
+        size: PageSize,
+    },
+}
 
-    for (_pdf_id, filepath) in &file_map {
-        if let Some(stem) = std::path::Path::new(filepath)
-            .file_stem()
-            .and_then(|s| s.to_str())
-        {
+impl PageSource {
+    fn validate(&self) -> SliceResult<()> {
+        match self {
+            PageSource::Pdf { rotation, .. } if rotation % 90 != 0 => {
+                Err(SliceError::invalid(format!(
+                    "Rotation must be a multiple of 90 degrees, got {}",
+                    rotation
+                )))
+            }
+            PageSource::Blank { size } | PageSource::Text { size, .. } => size.validate(),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MergeOptions {
+    /// Adds a top-level bookmark for every source PDF, named after the file and holding
+    /// that file's own bookmarks, and one for every image and text page.
+    pub bookmark_sources: bool,
+    /// Layout of the pages made from images.
+    pub image_pages: ImagePageOptions,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct MergeReport {
+    pub pages: usize,
+}
+
+/// Default file name offered when saving a merge of the files in `file_map`.
+pub fn merged_file_name(file_map: &HashMap<String, String>) -> String {
+    let mut parts = Vec::new();
+    for filepath in file_map.values() {
+        if let Some(stem) = Path::new(filepath).file_stem().and_then(|s| s.to_str()) {
             parts.push(stem.to_string());
         }
     }
-    let merged_name = if parts.is_empty() {
+    if parts.is_empty() {
         "slice-pdf-merged.pdf".to_string()
     } else {
         format!("slice-pdf-merged-{}.pdf", parts.join("-"))
+    }
+}
+
+/// Writes the pages named by `instructions` into a single PDF, see [`assemble_pdf`].
+pub fn merge_pdfs<W: Write>(
+    instructions: &[MergePageInstruction],
+    file_map: &HashMap<String, String>,
+    passwords: &Passwords,
+    options: &MergeOptions,
+    output: &mut W,
+    job: &Job,
+) -> SliceResult<MergeReport> {
+    let pages: Vec<PageSource> = instructions.iter().map(PageSource::from).collect();
+    assemble_pdf(&pages, file_map, passwords, options, output, job)
+}
+
+/// A source PDF, renumbered so its object ids do not clash with the other sources.
+struct SourcePdf {
+    doc: Document,
+    pages: BTreeMap<u32, ObjectId>,
+    page_ids: HashSet<ObjectId>,
+}
+
+/// Writes `pages` into a single PDF, in order. Bookmarks, links, named destinations and
+/// form fields of the source PDFs that lead to included pages are carried over.
+pub fn assemble_pdf<W: Write>(
+    pages: &[PageSource],
+    file_map: &HashMap<String, String>,
+    passwords: &Passwords,
+    options: &MergeOptions,
+    output: &mut W,
+    job: &Job,
+) -> SliceResult<MergeReport> {
+    if pages.is_empty() {
+        return Err(SliceError::invalid("No pages selected for merge"));
+    }
+    for page in pages {
+        page.validate()?;
+    }
+    options.image_pages.validate()?;
+    let path_of = |file_id: &String| {
+        file_map
+            .get(file_id)
+            .ok_or_else(|| SliceError::invalid(format!("File not found: {}", file_id)))
     };
-    let file_path = app
-        .dialog()
-        .file()
-        .set_file_name(merged_name)
-        .add_filter("PDF", &["pdf"][..])
-        .blocking_save_file();
-
-    let save_path = match file_path {
-        Some(path) => path.into_path().map_err(|_| "Invalid path")?,
-        _none => return Ok("Merge Cancelled".to_string()),
-    };
-    let save_path_for_closure = save_path.clone();
-
-    tauri::async_runtime::spawn_blocking(move || {
-        let mut target_doc = Document::with_version("1.7");
-        let mut max_id = 1;
-
-        let mut sources = HashMap::new();
-        for (pdf_id, filepath) in &file_map {
-            let mut doc = Document::load(filepath)
-                .map_err(|e| format!("Failed to load {}: {e}", filepath))?;
-            doc.renumber_objects_with(max_id);
-            max_id = doc.max_id + 1;
-            let pages = doc.get_pages();
-            sources.insert(pdf_id.clone(), (doc, pages));
+
+    // Load every PDF in order of first use
+    let mut pdf_ids: Vec<&String> = Vec::new();
+    for page in pages {
+        if let PageSource::Pdf { file_id, .. } = page {
+            if !pdf_ids.contains(&file_id) {
+                pdf_ids.push(file_id);
+            }
         }
+    }
+    let mut max_id = 1;
+    let mut sources = HashMap::new();
+    for (index, &file_id) in pdf_ids.iter().enumerate() {
+        let path = path_of(file_id)?;
+        job.report(Step::Load, index, pdf_ids.len(), Some(path.clone()));
+        job.checkpoint()?;
+        let password = passwords.get(file_id).map(String::as_str);
+        let mut doc = load_document(Path::new(path), password)?;
+        doc.renumber_objects_with(max_id);
+        max_id = doc.max_id + 1;
+        let source_pages = doc.get_pages();
+        let page_ids = source_pages.values().copied().collect();
+        sources.insert(
+            file_id,
+            SourcePdf {
+                doc,
+                pages: source_pages,
+                page_ids,
+            },
+        );
+    }
 
-        // Collect page IDs in order and copy all objects
-        let mut page_ids_to_include = Vec::new();
-        for instr in &instructions {
-            if let Some((source_doc, page_map)) = sources.get(&instr.sourcepdfid) {
-                if let Some(&page_id) = page_map.get(&instr.source_page_number) {
-                    // Copy all objects from this source document
-                    for (&id, obj) in &source_doc.objects {
-                        if !target_doc.objects.contains_key(&id) {
-                            target_doc.objects.insert(id, obj.clone());
-                        }
-                    }
-                    page_ids_to_include.push(page_id);
+    let mut target_doc = Document::with_version("1.7");
+    // Fresh ids go after everything copied from the sources.
+    target_doc.max_id = max_id;
+
+    // Copy or draw each page, noting its rotation
+    let mut page_ids = Vec::with_capacity(pages.len());
+    let mut sections: Vec<OutlineSection> = Vec::new();
+    let mut bookmarked = HashSet::new();
+    for (index, page) in pages.iter().enumerate() {
+        let (page_id, rotation) = match page {
+            PageSource::Pdf {
+                file_id,
+                page_number,
+                rotation,
+            } => {
+                let path = path_of(file_id)?;
+                job.report(
+                    Step::Page,
+                    index,
+                    pages.len(),
+                    Some(page_label(path, *page_number)),
+                );
+                job.checkpoint()?;
+                let source = &sources[file_id];
+                let &page_id =
+                    source
+                        .pages
+                        .get(page_number)
+                        .ok_or_else(|| SliceError::PageOutOfRange {
+                            path: path.clone(),
+                            page: *page_number,
+                            page_count: source.pages.len(),
+                        })?;
+                copy_page(&source.doc, &source.page_ids, page_id, &mut target_doc)?;
+                if bookmarked.insert(file_id) {
+                    sections.push(OutlineSection {
+                        title: file_title(path),
+                        first_page: page_id,
+                        source: Some(&source.doc),
+                    });
                 }
+                (page_id, *rotation)
             }
-        }
+            PageSource::Image { file_id } => {
+                let path = path_of(file_id)?;
+                job.report(Step::Image, index, pages.len(), Some(path.clone()));
+                job.checkpoint()?;
+                let page_id = image_page(&mut target_doc, Path::new(path), &options.image_pages)?;
+                sections.push(OutlineSection {
+                    title: file_title(path),
+                    first_page: page_id,
+                    source: None,
+                });
+                (page_id, 0)
+            }
+            PageSource::Blank { size } => {
+                job.report(Step::Page, index, pages.len(), None);
+                job.checkpoint()?;
+                (blank_page(&mut target_doc, *size), 0)
+            }
+            PageSource::Text { text, size } => {
+                job.report(Step::Page, index, pages.len(), None);
+                job.checkpoint()?;
+                let page_id = text_page(&mut target_doc, text, *size);
+                if let Some(title) = text.lines().map(str::trim).find(|line| !line.is_empty()) {
+                    sections.push(OutlineSection {
+                        title: title.to_string(),
+                        first_page: page_id,
+                        source: None,
+                    });
+                }
+                (page_id, 0)
+            }
+        };
+        page_ids.push((page_id, rotation));
+    }
 
-        let pages_id = target_doc.new_object_id();
+    // Links are settled before pages are duplicated, so copies get the updated ones
+    let included: HashSet<ObjectId> = page_ids.iter().map(|&(page_id, _)| page_id).collect();
+    let mut destinations = MergedDestinations::default();
+    for file_id in &pdf_ids {
+        destinations.add_source(&mut target_doc, &sources[file_id].doc, &included);
+    }
 
-        if page_ids_to_include.is_empty() {
-            return Err("No pages selected for merge".to_string());
+    // Repeated pages get their own dictionary, copied before any rotation is applied
+    let mut placed = HashSet::new();
+    for (page_id, _) in page_ids.iter_mut() {
+        if !placed.insert(*page_id) {
+            *page_id = duplicate_page(&mut target_doc, *page_id)?;
         }
+    }
 
-        let mut final_kids = Vec::new();
-        for &page_id in &page_ids_to_include {
-            if let Ok(page_dict) = target_doc
-                .get_object_mut(page_id)
-                .and_then(|o| o.as_dict_mut())
-            {
-                page_dict.set("Parent", pages_id);
-                final_kids.push(Object::Reference(page_id));
+    let pages_id = target_doc.new_object_id();
+    let mut kids = Vec::with_capacity(page_ids.len());
+    for (page_id, rotation) in page_ids {
+        if let Ok(page_dict) = target_doc
+            .get_object_mut(page_id)
+            .and_then(|o| o.as_dict_mut())
+        {
+            page_dict.set("Parent", pages_id);
+            if rotation != 0 {
+                let current = page_dict
+                    .get(b"Rotate")
+                    .and_then(Object::as_i64)
+                    .unwrap_or(0);
+                page_dict.set("Rotate", (current + rotation as i64).rem_euclid(360));
             }
+            kids.push(page_id);
         }
+    }
 
-        // Insert Pages dictionary
-        let pages_dict = dictionary! {
-            "Type" => "Pages",
-            "Count" => final_kids.len() as i64,
-            "Kids" => final_kids,
-        };
-        target_doc
-            .objects
-            .insert(pages_id, Object::Dictionary(pages_dict));
+    let form_sources: Vec<&Document> = pdf_ids.iter().map(|id| &sources[id].doc).collect();
+    let form = merged_form(&mut target_doc, &form_sources, &kids);
 
-        // Create Catalog
-        let catalog_id = target_doc.new_object_id();
-        let catalog = dictionary! {
-            "Type" => "Catalog",
-            "Pages" => pages_id,
-        };
-        target_doc
-            .objects
-            .insert(catalog_id, Object::Dictionary(catalog));
-
-        // Set trailer and max_id
-        target_doc.trailer.set("Root", catalog_id);
-        target_doc.max_id = max_id;
-
-        // Save
-        target_doc.compress();
-        target_doc
-            .save(&save_path_for_closure)
-            .map_err(|e| format!("Failed to save PDF: {e}"))?;
-
-        Ok::<(), String>(())
-    })
-    .await
-    .map_err(|e| e.to_string())??;
-
-    Ok(format!("PDF merged successfully at {:?}", save_path))
+    // Insert the Pages dictionary
+    let page_count = kids.len();
+    let pages_dict = dictionary! {
+        "Type" => "Pages",
+        "Count" => page_count as i64,
+        "Kids" => kids.into_iter().map(Object::Reference).collect::<Vec<_>>(),
+    };
+    target_doc
+        .objects
+        .insert(pages_id, Object::Dictionary(pages_dict));
+
+    let outlines = merged_outline(&mut target_doc, sections, &included, options);
+
+    // Create Catalog
+    let catalog_id = target_doc.new_object_id();
+    let mut catalog = dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    };
+    if let Some(outlines) = outlines {
+        catalog.set("Outlines", outlines);
+    }
+    if let Some(form) = form {
+        catalog.set("AcroForm", form);
+    }
+    destinations.write(&mut catalog);
+    target_doc
+        .objects
+        .insert(catalog_id, Object::Dictionary(catalog));
+
+    // Set trailer
+    target_doc.trailer.set("Root", catalog_id);
+
+    // Save
+    let optimize = OptimizeOptions::default();
+    optimize_document(&mut target_doc, &optimize);
+    target_doc.compress();
+    save_optimized(&mut target_doc, &optimize, output, job)?;
+
+    Ok(MergeReport { pages: page_count })
 }
 
-pub async fn merge_all(
-    app: tauri::AppHandle,
-    instructions: Vec<MergeInstruction>,
-    file_map: HashMap<String, String>,
-) -> Result<(), String> {
-    let mut parts = Vec::new();
-    for (_pdf_id, filepath) in &file_map {
-        if let Some(stem) = std::path::Path::new(filepath)
-            .file_stem()
-            .and_then(|s| s.to_str())
-        {
-            parts.push(stem.to_string());
+/// Where a run of output pages comes from, for building the merged outline.
+struct OutlineSection<'a> {
+    title: String,
+    first_page: ObjectId,
+    /// Document whose own bookmarks are carried over, `None` for image pages.
+    source: Option<&'a Document>,
+}
+
+/// Writes the outline of the merged document: the bookmarks of each source that lead to
+/// an included page, grouped under one bookmark per source with
+/// [`MergeOptions::bookmark_sources`]. Returns the outline root, if any.
+fn merged_outline(
+    target: &mut Document,
+    sections: Vec<OutlineSection>,
+    included: &HashSet<ObjectId>,
+    options: &MergeOptions,
+) -> Option<ObjectId> {
+    // Sources were renumbered apart, so page ids never clash between them
+    let remap = |page: ObjectId| included.contains(&page).then_some(page);
+
+    let mut bookmarks = Vec::new();
+    for section in sections {
+        let own = section
+            .source
+            .map(|source| read_outline(source, &remap))
+            .unwrap_or_default();
+        if options.bookmark_sources {
+            bookmarks.push(Bookmark::new(&section.title, section.first_page, own));
+        } else {
+            bookmarks.extend(own);
         }
     }
-    let merged_name = if parts.is_empty() {
-        "slice-pdf-merged.pdf".to_string()
-    } else {
-        format!("slice-pdf-merged-{}.pdf", parts.join("-"))
-    };
+    write_outline(target, bookmarks)
+}
 
-    let save_path = app
-        .dialog()
-        .file()
-        .set_file_name(merged_name)
-        .blocking_save_file()
-        .ok_or("Save cancelled")?
-        .into_path()
-        .map_err(|_| "Invalid Path")?;
-
-    tauri::async_runtime::spawn_blocking(move || {
-        let mut target_doc = Document::with_version("1.7");
-        let mut max_id = 1;
-        let mut source_docs: HashMap<String, Document> = HashMap::new();
-        let mut final_page_ids = Vec::new();
-
-        // Process all instructions in order
-        for (_index, instr) in instructions.iter().enumerate() {
-            let path = file_map.get(&instr.file_id).ok_or("File not found")?;
-
-            if instr.kind == "pdf" {
-                // Load or get cached PDF document
-                if !source_docs.contains_key(path) {
-                    let mut d = Document::load(path).map_err(|e| e.to_string())?;
-                    d.renumber_objects_with(max_id);
-                    max_id = d.max_id + 1;
-                    source_docs.insert(path.clone(), d);
-                }
+/// Bookmark title for the file at `path`.
+fn file_title(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
 
-                let doc = source_docs.get(path).unwrap();
-                let pages = doc.get_pages();
-
-                // Get page ID - pages are 1-indexed in lopdf
-                let &source_page_id = pages.get(&instr.page_number).ok_or_else(|| {
-                    format!(
-                        "Page {} not found in PDF (has {} pages)",
-                        instr.page_number,
-                        pages.len()
-                    )
-                })?;
-
-                // Copy all objects from this source document
-                for (&id, obj) in &doc.objects {
-                    if !target_doc.objects.contains_key(&id) {
-                        target_doc.objects.insert(id, obj.clone());
-                    }
-                }
+/// Copies page `page_id` of `source` into `target` along with everything it uses:
+/// content streams, resources, fonts, XObjects and annotations. Attributes the page
+/// inherits are copied onto it first, since it is about to get a new parent.
+///
+/// `/Parent` is not followed and references to the other pages of `source`
+/// (`source_pages`) are left dangling, so picking one page out of a large document does
+/// not drag the rest along.
+fn copy_page(
+    source: &Document,
+    source_pages: &HashSet<ObjectId>,
+    page_id: ObjectId,
+    target: &mut Document,
+) -> SliceResult<()> {
+    let page = resolved_page(source, page_id)?;
+    let roots = page
+        .iter()
+        .filter(|(key, _)| key.as_slice() != b"Parent")
+        .map(|(_, value)| value);
 
-                // Store the page ID in order
-                final_page_ids.push(source_page_id);
-            } else {
-                println!("DEBUG: Creating image page...");
-                // Handle Image -> New PDF Page
-                let img = image::open(path).map_err(|e| e.to_string())?;
-
-                // Standard PDF page sizes (in points: 1 point = 1/72 inch)
-                const A4_WIDTH: f32 = 595.0; // 8.27 inches
-                const A4_HEIGHT: f32 = 842.0; // 11.69 inches
-                const MAX_DPI: f32 = 150.0; // Maximum DPI for embedded images
-
-                let (orig_width, orig_height) = img.dimensions();
-
-                // Calculate target dimensions to fit A4 while maintaining aspect ratio
-                let img_aspect = orig_width as f32 / orig_height as f32;
-                let page_aspect = A4_WIDTH / A4_HEIGHT;
-
-                let (page_width, page_height) = if img_aspect > page_aspect {
-                    // Image is wider - fit to width
-                    (A4_WIDTH, A4_WIDTH / img_aspect)
-                } else {
-                    // Image is taller - fit to height
-                    (A4_HEIGHT * img_aspect, A4_HEIGHT)
-                };
-
-                // Calculate optimal image resolution (max 150 DPI to reduce file size)
-                let target_width = ((page_width / 72.0) * MAX_DPI) as u32;
-                let target_height = ((page_height / 72.0) * MAX_DPI) as u32;
-
-                // Resize image if it's too large
-                let img = if orig_width > target_width || orig_height > target_height {
-                    img.resize(
-                        target_width,
-                        target_height,
-                        image::imageops::FilterType::Lanczos3,
-                    )
-                } else {
-                    img
-                };
-
-                let img_rgb = img.to_rgb8();
-                let (final_width, final_height) = img_rgb.dimensions();
-
-                // Compress image data using flate2
-                use flate2::write::ZlibEncoder;
-                use flate2::Compression;
-                use std::io::Write;
-
-                let raw_data = img_rgb.into_raw();
-                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
-                encoder.write_all(&raw_data).map_err(|e| e.to_string())?;
-                let compressed_data = encoder.finish().map_err(|e| e.to_string())?;
-
-                // Create image XObject with proper ID from max_id
-                let img_obj_id = (max_id, 0);
-                max_id += 1;
-                let stream = Stream::new(
-                    dictionary! {
-                        "Type" => "XObject",
-                        "Subtype" => "Image",
-                        "Width" => final_width as i64,
-                        "Height" => final_height as i64,
-                        "ColorSpace" => "DeviceRGB",
-                        "BitsPerComponent" => 8,
-                        "Filter" => "FlateDecode",
-                    },
-                    compressed_data,
-                );
-                target_doc
-                    .objects
-                    .insert(img_obj_id, Object::Stream(stream));
-
-                // Create content stream
-                let content = format!("q {} 0 0 {} 0 0 cm /Im1 Do Q", page_width, page_height);
-                let content_obj_id = (max_id, 0);
-                max_id += 1;
-                target_doc.objects.insert(
-                    content_obj_id,
-                    Object::Stream(Stream::new(dictionary! {}, content.into_bytes())),
-                );
+    for id in reachable_objects(source, roots, source_pages) {
+        if let Some(object) = source.objects.get(&id) {
+            target.objects.entry(id).or_insert_with(|| object.clone());
+        }
+    }
+    target.objects.insert(page_id, Object::Dictionary(page));
+    Ok(())
+}
+
+/// Adds a second copy of page `page_id` to `doc` and returns its id, so a page picked
+/// twice ends up as two distinct entries in `/Kids`. Contents and resources stay shared;
+/// annotations are copied too, since each one belongs to a single page through `/P`.
+/// Copied widgets become further widgets of the same form field, so both pages show and
+/// edit one value.
+fn duplicate_page(doc: &mut Document, page_id: ObjectId) -> SliceResult<ObjectId> {
+    let mut page = doc
+        .get_dictionary(page_id)
+        .map_err(|e| SliceError::internal(format!("Invalid page {:?}: {}", page_id, e)))?
+        .clone();
+    let copy_id = doc.new_object_id();
+
+    let mut annotations: Vec<ObjectId> = page
+        .get(b"Annots")
+        .ok()
+        .and_then(|annots| doc.dereference(annots).ok())
+        .and_then(|(_, annots)| annots.as_array().ok())
+        .map(|annots| {
+            annots
+                .iter()
+                .filter_map(|a| a.as_reference().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // A field sharing its dictionary with its widget can't take a second widget
+    for annotation in annotations.iter_mut() {
+        if let Some(widget) = split_widget(doc, *annotation) {
+            replace_annotation(doc, page_id, *annotation, widget);
+            *annotation = widget;
+        }
+    }
 
-                // Create Page object with A4-based dimensions
-                let page_obj_id = (max_id, 0);
-                max_id += 1;
-                let page_dict = dictionary! {
-                    "Type" => "Page",
-                    "Contents" => content_obj_id,
-                    "MediaBox" => vec![0.into(), 0.into(), page_width.into(), page_height.into()],
-                    "Resources" => dictionary! {
-                        "XObject" => dictionary! { "Im1" => img_obj_id }
-                    },
-                };
-                target_doc
-                    .objects
-                    .insert(page_obj_id, Object::Dictionary(page_dict));
-
-                // Store the page ID in order
-                final_page_ids.push(page_obj_id);
+    if !annotations.is_empty() {
+        let copies: HashMap<ObjectId, ObjectId> = annotations
+            .iter()
+            .map(|&id| (id, doc.new_object_id()))
+            .collect();
+        for (&original, &copy) in &copies {
+            let Ok(mut annotation) = doc.get_dictionary(original).cloned() else {
+                continue;
+            };
+            annotation.set("P", copy_id);
+            // Keep markup annotations and their popups pointing at each other.
+            for key in [b"Popup".as_slice(), b"Parent".as_slice()] {
+                let linked = annotation.get(key).and_then(Object::as_reference);
+                if let Some(&linked) = linked.ok().and_then(|id| copies.get(&id)) {
+                    annotation.set(key, linked);
+                }
+            }
+            let is_widget = annotation
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .is_ok_and(|subtype| subtype == b"Widget");
+            let field = annotation
+                .get(b"Parent")
+                .and_then(Object::as_reference)
+                .ok()
+                .filter(|_| is_widget);
+            doc.objects.insert(copy, Object::Dictionary(annotation));
+            if let Some(field) = field {
+                add_kid(doc, field, copy);
             }
         }
+        let annots: Vec<Object> = annotations
+            .iter()
+            .map(|id| Object::Reference(copies[id]))
+            .collect();
+        page.set("Annots", annots);
+    }
 
-        // Create Pages Root with proper ID
-        let pages_id = (max_id, 0);
-        max_id += 1;
-
-        // Update Parent references for all pages
-        for &page_id in &final_page_ids {
-            if let Ok(page_dict) = target_doc
-                .get_object_mut(page_id)
-                .and_then(|o| o.as_dict_mut())
-            {
-                page_dict.set("Parent", pages_id);
+    doc.objects.insert(copy_id, Object::Dictionary(page));
+    Ok(copy_id)
+}
+
+/// Swaps annotation `old` for `new` in the `/Annots` of page `page_id`.
+fn replace_annotation(doc: &mut Document, page_id: ObjectId, old: ObjectId, new: ObjectId) {
+    let annots_id = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Annots"))
+        .and_then(Object::as_reference);
+    let annots = match annots_id {
+        Ok(id) => doc.get_object_mut(id),
+        Err(_) => doc
+            .get_dictionary_mut(page_id)
+            .and_then(|page| page.get_mut(b"Annots")),
+    };
+    if let Ok(Object::Array(annots)) = annots {
+        for annot in annots.iter_mut() {
+            if annot.as_reference().is_ok_and(|id| id == old) {
+                *annot = Object::Reference(new);
             }
         }
+    }
+}
 
-        // Insert the Pages dictionary
-        let pages_dict = dictionary! {
-            "Type" => "Pages",
-            "Count" => final_page_ids.len() as i64,
-            "Kids" => final_page_ids
-                .into_iter()
-                .map(Object::Reference)
-                .collect::<Vec<_>>(),
-        };
-        target_doc
-            .objects
-            .insert(pages_id, Object::Dictionary(pages_dict));
+/// Appends widget `kid` to the `/Kids` of form field `field`.
+fn add_kid(doc: &mut Document, field: ObjectId, kid: ObjectId) {
+    let Ok(field) = doc.get_dictionary_mut(field) else {
+        return;
+    };
+    // Anything odder is left to `merged_form`, which rebuilds every `/Kids` it walks
+    if let Ok(Object::Array(kids)) = field.get_mut(b"Kids") {
+        kids.push(Object::Reference(kid));
+    }
+}
 
-        // Create Catalog
-        let catalog_id = target_doc.new_object_id();
-        let catalog = dictionary! {
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{decode_text_string, Dictionary};
+
+    /// A one-page form with a field that shares its dictionary with its widget, `name`,
+    /// and one whose widget is a kid of its own, `email`.
+    fn form_pdf() -> Document {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let page_id = doc.new_object_id();
+        let name_id = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Widget",
+            "FT" => "Tx",
+            "T" => Object::string_literal("name"),
+            "V" => Object::string_literal("Ada"),
+            "Rect" => vec![72.into(), 700.into(), 300.into(), 720.into()],
+            "P" => page_id,
+            "AA" => dictionary! {
+                "Fo" => dictionary! { "S" => "JavaScript", "JS" => Object::string_literal("focus()") },
+                "K" => dictionary! { "S" => "JavaScript", "JS" => Object::string_literal("keystroke()") },
+            },
+        });
+        let email_id = doc.new_object_id();
+        let email_widget_id = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Widget",
+            "Parent" => email_id,
+            "Rect" => vec![72.into(), 650.into(), 300.into(), 670.into()],
+            "P" => page_id,
+        });
+        doc.objects.insert(
+            email_id,
+            Object::Dictionary(dictionary! {
+                "FT" => "Tx",
+                "T" => Object::string_literal("email"),
+                "Kids" => vec![email_widget_id.into()],
+            }),
+        );
+        doc.objects.insert(
+            page_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+                "Annots" => vec![name_id.into(), email_widget_id.into()],
+            }),
+        );
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
             "Type" => "Catalog",
             "Pages" => pages_id,
+            "AcroForm" => dictionary! {
+                "Fields" => vec![name_id.into(), email_id.into()],
+            },
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    /// A document of `count` pages. Each page draws an image of its own and shares one
+    /// font with the others.
+    fn pages_pdf(count: u32) -> Document {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let mut kids = Vec::new();
+        for number in 1..=count {
+            let image_id = doc.add_object(lopdf::Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => 1,
+                    "Height" => 1,
+                    "ColorSpace" => "DeviceGray",
+                    "BitsPerComponent" => 8,
+                },
+                vec![number as u8],
+            ));
+            let content = format!(
+                "q 72 0 0 72 0 0 cm /Im1 Do Q BT /F1 12 Tf (page {}) Tj ET",
+                number
+            );
+            let content_id =
+                doc.add_object(lopdf::Stream::new(dictionary! {}, content.into_bytes()));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+                "Contents" => content_id,
+                "Resources" => dictionary! {
+                    "Font" => dictionary! { "F1" => font_id },
+                    "XObject" => dictionary! { "Im1" => image_id },
+                },
+            });
+            kids.push(page_id.into());
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => count as i64,
+                "Kids" => kids,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    fn pdf_page(file_id: &str, page_number: u32) -> PageSource {
+        PageSource::Pdf {
+            file_id: file_id.to_string(),
+            page_number,
+            rotation: 0,
+        }
+    }
+
+    /// Saves `files` as `{id}.pdf` and assembles `pages` from them.
+    fn merge(
+        files: Vec<(&str, Document)>,
+        pages: &[PageSource],
+        options: &MergeOptions,
+    ) -> Document {
+        merge_with_images(files, &[], pages, options)
+    }
+
+    /// Like [`merge`], with a small PNG saved as `{id}.png` for each of `images`.
+    fn merge_with_images(
+        files: Vec<(&str, Document)>,
+        images: &[&str],
+        pages: &[PageSource],
+        options: &MergeOptions,
+    ) -> Document {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut file_map = HashMap::new();
+        for (file_id, mut doc) in files {
+            let path = dir.path().join(format!("{}.pdf", file_id));
+            doc.save(&path).unwrap();
+            file_map.insert(file_id.to_string(), path.display().to_string());
+        }
+        for file_id in images {
+            let path = dir.path().join(format!("{}.png", file_id));
+            image::RgbImage::new(2, 2).save(&path).unwrap();
+            file_map.insert(file_id.to_string(), path.display().to_string());
+        }
+        let mut output = Vec::new();
+        assemble_pdf(
+            pages,
+            &file_map,
+            &Passwords::new(),
+            options,
+            &mut output,
+            &Job::silent(),
+        )
+        .unwrap();
+        Document::load_mem(&output).unwrap()
+    }
+
+    /// Contents of every stream in `doc` that is not an object or cross-reference stream.
+    fn stream_contents(doc: &Document) -> Vec<Vec<u8>> {
+        doc.objects
+            .values()
+            .filter_map(|object| object.as_stream().ok())
+            .filter(|stream| {
+                !matches!(
+                    stream.dict.get(b"Type").and_then(Object::as_name),
+                    Ok(b"ObjStm" | b"XRef")
+                )
+            })
+            .map(|stream| {
+                stream
+                    .decompressed_content()
+                    .unwrap_or(stream.content.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn picking_one_page_leaves_the_other_pages_behind() {
+        let all = merge(
+            vec![("doc", pages_pdf(3))],
+            &[pdf_page("doc", 1), pdf_page("doc", 2), pdf_page("doc", 3)],
+            &MergeOptions::default(),
+        );
+        let one = merge(
+            vec![("doc", pages_pdf(3))],
+            &[pdf_page("doc", 2)],
+            &MergeOptions::default(),
+        );
+
+        // Each page left out takes its dictionary, content stream and image with it
+        assert_eq!(one.objects.len() + 2 * 3, all.objects.len());
+        let streams = stream_contents(&one);
+        assert!(
+            streams.contains(&b"q 72 0 0 72 0 0 cm /Im1 Do Q BT /F1 12 Tf (page 2) Tj ET".to_vec())
+        );
+        assert!(streams.contains(&vec![2]));
+        assert!(!streams.contains(&vec![1]) && !streams.contains(&vec![3]));
+    }
+
+    #[test]
+    fn inherited_page_attributes_move_onto_the_page() {
+        // The pages leave their box, resources and rotation to the `/Pages` node
+        let mut source = pages_pdf(2);
+        let source_pages = source.get_pages();
+        let mut inherited = Dictionary::new();
+        for page_id in source_pages.values() {
+            let page = source.get_dictionary_mut(*page_id).unwrap();
+            for key in [b"MediaBox".as_slice(), b"Resources".as_slice()] {
+                inherited.set(key, page.remove(key).unwrap());
+            }
+        }
+        inherited.set("Rotate", 90);
+        let pages_id = source
+            .catalog()
+            .unwrap()
+            .get(b"Pages")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        source
+            .get_dictionary_mut(pages_id)
+            .unwrap()
+            .extend(&inherited);
+
+        let merged = merge(
+            vec![("doc", source)],
+            &[
+                PageSource::Pdf {
+                    file_id: "doc".to_string(),
+                    page_number: 1,
+                    rotation: 90,
+                },
+                pdf_page("doc", 2),
+            ],
+            &MergeOptions::default(),
+        );
+
+        let pages = merged.get_pages();
+        for (number, rotation) in [(1, 180), (2, 90)] {
+            let page = merged.get_dictionary(pages[&number]).unwrap();
+            assert_eq!(
+                page.get(b"Rotate").and_then(Object::as_i64).unwrap(),
+                rotation
+            );
+            let media_box: Vec<i64> = page
+                .get(b"MediaBox")
+                .and_then(Object::as_array)
+                .unwrap()
+                .iter()
+                .map(|side| side.as_i64().unwrap())
+                .collect();
+            assert_eq!(media_box, [0, 0, 612, 792]);
+            let resources = page
+                .get(b"Resources")
+                .and_then(|resources| merged.dereference(resources))
+                .and_then(|(_, resources)| resources.as_dict())
+                .unwrap();
+            assert!(resources.has(b"Font") && resources.has(b"XObject"));
+        }
+    }
+
+    /// Gives `doc` one top-level bookmark per entry of `items`, leading to the given page.
+    fn add_outline(doc: &mut Document, items: &[(&str, u32)]) {
+        let pages = doc.get_pages();
+        let bookmarks = items
+            .iter()
+            .map(|&(title, page)| Bookmark::new(title, pages[&page], Vec::new()))
+            .collect();
+        let root = write_outline(doc, bookmarks).unwrap();
+        doc.catalog_mut().unwrap().set("Outlines", root);
+    }
+
+    /// Title and output page number of every bookmark of `doc` in order, children
+    /// indented under their parent.
+    fn outline_entries(doc: &Document) -> Vec<(String, u32)> {
+        let numbers: HashMap<ObjectId, u32> = doc
+            .get_pages()
+            .into_iter()
+            .map(|(number, id)| (id, number))
+            .collect();
+        let mut entries = Vec::new();
+        let mut pending: Vec<(usize, Bookmark)> = read_outline(doc, &Some)
+            .into_iter()
+            .rev()
+            .map(|bookmark| (0, bookmark))
+            .collect();
+        while let Some((depth, bookmark)) = pending.pop() {
+            let title = decode_text_string(&bookmark.title).unwrap();
+            let page = numbers[&bookmark.dest[0].as_reference().unwrap()];
+            entries.push((format!("{}{}", "  ".repeat(depth), title), page));
+            pending.extend(
+                bookmark
+                    .children
+                    .into_iter()
+                    .rev()
+                    .map(|child| (depth + 1, child)),
+            );
+        }
+        entries
+    }
+
+    /// Two bookmarked sources, `a` with three pages and `b` with two, merged as a1, b2, a2
+    /// and an image; page 3 of `a` and page 1 of `b`, and so their bookmarks, are left out.
+    fn merge_bookmarked(options: &MergeOptions) -> Vec<(String, u32)> {
+        let mut a = pages_pdf(3);
+        add_outline(&mut a, &[("A one", 1), ("A three", 3), ("A two", 2)]);
+        let mut b = pages_pdf(2);
+        add_outline(&mut b, &[("B one", 1), ("B two", 2)]);
+        let pages = [
+            pdf_page("a", 1),
+            pdf_page("b", 2),
+            pdf_page("a", 2),
+            PageSource::Image {
+                file_id: "logo".to_string(),
+            },
+        ];
+        let merged = merge_with_images(vec![("a", a), ("b", b)], &["logo"], &pages, options);
+        outline_entries(&merged)
+    }
+
+    fn entries(expected: &[(&str, u32)]) -> Vec<(String, u32)> {
+        expected
+            .iter()
+            .map(|&(title, page)| (title.to_string(), page))
+            .collect()
+    }
+
+    #[test]
+    fn bookmarks_follow_their_pages_into_the_merge() {
+        let outline = merge_bookmarked(&MergeOptions::default());
+        assert_eq!(
+            outline,
+            entries(&[("A one", 1), ("A two", 3), ("B two", 2)])
+        );
+    }
+
+    #[test]
+    fn bookmark_sources_groups_bookmarks_under_each_file() {
+        let options = MergeOptions {
+            bookmark_sources: true,
+            ..MergeOptions::default()
         };
-        target_doc
-            .objects
-            .insert(catalog_id, Object::Dictionary(catalog));
-
-        // Set trailer and max_id
-        target_doc.trailer.set("Root", catalog_id);
-        target_doc.max_id = max_id;
-
-        // Save
-        target_doc.compress();
-        target_doc.save(save_path).map_err(|e| e.to_string())?;
-
-        Ok::<(), String>(())
-    })
-    .await
-    .map_err(|e| e.to_string())?
+        let outline = merge_bookmarked(&options);
+        assert_eq!(
+            outline,
+            entries(&[
+                ("a", 1),
+                ("  A one", 1),
+                ("  A two", 3),
+                ("b", 2),
+                ("  B two", 2),
+                ("logo", 4),
+            ])
+        );
+    }
+
+    /// A three-page document with named destinations `keep` (page 2) and `drop` (page 3),
+    /// and links on page 1 to both pages and both names.
+    fn linked_pdf() -> Document {
+        let mut doc = pages_pdf(3);
+        let pages = doc.get_pages();
+        let fit = |page: ObjectId| Object::Array(vec![page.into(), "Fit".into()]);
+        let links: Vec<Object> = [
+            fit(pages[&2]),
+            fit(pages[&3]),
+            Object::Name(b"keep".to_vec()),
+            Object::Name(b"drop".to_vec()),
+        ]
+        .into_iter()
+        .map(|dest| {
+            doc.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Link",
+                "Rect" => vec![72.into(), 72.into(), 144.into(), 144.into()],
+                "Dest" => dest,
+            })
+            .into()
+        })
+        .collect();
+        doc.get_dictionary_mut(pages[&1])
+            .unwrap()
+            .set("Annots", links);
+        doc.catalog_mut().unwrap().set(
+            "Dests",
+            dictionary! { "keep" => fit(pages[&2]), "drop" => fit(pages[&3]) },
+        );
+        doc
+    }
+
+    /// Where `dest` leads, as `page {n}` for an explicit destination or `name {key}` for a
+    /// named one.
+    fn describe_destination(doc: &Document, dest: &Object) -> String {
+        let numbers: HashMap<ObjectId, u32> = doc
+            .get_pages()
+            .into_iter()
+            .map(|(number, id)| (id, number))
+            .collect();
+        match doc.dereference(dest).unwrap().1 {
+            Object::Name(key) => format!("name {}", String::from_utf8_lossy(key)),
+            Object::Array(dest) => format!("page {}", numbers[&dest[0].as_reference().unwrap()]),
+            other => panic!("unexpected destination {:?}", other),
+        }
+    }
+
+    /// Destinations of the links on page `number` of `doc`.
+    fn link_destinations(doc: &Document, number: u32) -> Vec<String> {
+        let page = doc.get_dictionary(doc.get_pages()[&number]).unwrap();
+        let Ok(annots) = page.get(b"Annots") else {
+            return Vec::new();
+        };
+        references(annots)
+            .into_iter()
+            .map(|id| {
+                let link = doc.get_dictionary(id).unwrap();
+                describe_destination(doc, link.get(b"Dest").unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn links_and_named_destinations_follow_the_merged_pages() {
+        let pages = [
+            pdf_page("a", 1),
+            pdf_page("a", 2),
+            pdf_page("b", 1),
+            pdf_page("b", 2),
+        ];
+        let merged = merge(
+            vec![("a", linked_pdf()), ("b", linked_pdf())],
+            &pages,
+            &MergeOptions::default(),
+        );
+
+        // Links to page 3 and to `drop`, which leads there, are gone; the second `keep`
+        // is renamed so it does not clash with the first
+        assert_eq!(link_destinations(&merged, 1), ["page 2", "name keep"]);
+        assert_eq!(link_destinations(&merged, 3), ["page 4", "name keep_1"]);
+        let dests = merged
+            .catalog()
+            .unwrap()
+            .get(b"Dests")
+            .and_then(Object::as_dict)
+            .unwrap();
+        let dests: Vec<(String, String)> = dests
+            .iter()
+            .map(|(key, dest)| {
+                (
+                    String::from_utf8_lossy(key).to_string(),
+                    describe_destination(&merged, dest),
+                )
+            })
+            .collect();
+        assert_eq!(
+            dests,
+            [
+                ("keep".to_string(), "page 2".to_string()),
+                ("keep_1".to_string(), "page 4".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn fields_from_different_sources_get_distinct_names() {
+        let merged = merge(
+            vec![("first", form_pdf()), ("second", form_pdf())],
+            &[pdf_page("first", 1), pdf_page("second", 1)],
+            &MergeOptions::default(),
+        );
+
+        let form = merged
+            .catalog()
+            .and_then(|catalog| catalog.get(b"AcroForm"))
+            .and_then(|form| merged.dereference(form))
+            .and_then(|(_, form)| form.as_dict())
+            .unwrap();
+        let mut names: Vec<String> = references(form.get(b"Fields").unwrap())
+            .into_iter()
+            .map(|id| field_name(merged.get_dictionary(id).unwrap()))
+            .collect();
+        names.sort();
+        assert_eq!(names, ["email", "email_1", "name", "name_1"]);
+    }
+
+    fn field_name(field: &Dictionary) -> String {
+        decode_text_string(field.get(b"T").unwrap()).unwrap()
+    }
+
+    fn references(object: &Object) -> Vec<ObjectId> {
+        object
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item.as_reference().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn repeated_form_pages_share_their_fields() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("form.pdf");
+        form_pdf().save(&path).unwrap();
+        let file_map = HashMap::from([("form".to_string(), path.display().to_string())]);
+        let pages = [
+            PageSource::Pdf {
+                file_id: "form".to_string(),
+                page_number: 1,
+                rotation: 0,
+            },
+            PageSource::Pdf {
+                file_id: "form".to_string(),
+                page_number: 1,
+                rotation: 90,
+            },
+        ];
+
+        let mut output = Vec::new();
+        assemble_pdf(
+            &pages,
+            &file_map,
+            &Passwords::new(),
+            &MergeOptions::default(),
+            &mut output,
+            &Job::silent(),
+        )
+        .unwrap();
+
+        let doc = Document::load_mem(&output).unwrap();
+        let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        assert_eq!(page_ids.len(), 2);
+        let form = doc
+            .catalog()
+            .and_then(|catalog| catalog.get(b"AcroForm"))
+            .and_then(|form| doc.dereference(form))
+            .and_then(|(_, form)| form.as_dict())
+            .unwrap();
+        let fields = references(form.get(b"Fields").unwrap());
+
+        // Still two fields under their own names, each with a widget on both pages
+        let mut names = Vec::new();
+        for field_id in fields {
+            let field = doc.get_dictionary(field_id).unwrap();
+            names.push(field_name(field));
+            assert!(!field.has(b"Subtype") && !field.has(b"Rect"));
+
+            let widgets = references(field.get(b"Kids").unwrap());
+            let mut shown_on = Vec::new();
+            for widget_id in widgets {
+                let widget = doc.get_dictionary(widget_id).unwrap();
+                assert!(!widget.has(b"T"));
+                assert_eq!(
+                    widget.get(b"Parent").unwrap().as_reference().unwrap(),
+                    field_id
+                );
+                let page_id = widget.get(b"P").unwrap().as_reference().unwrap();
+                let annots =
+                    references(doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap());
+                assert!(annots.contains(&widget_id));
+                shown_on.push(page_id);
+            }
+            shown_on.sort();
+            assert_eq!(shown_on, page_ids);
+        }
+        names.sort();
+        assert_eq!(names, ["email", "name"]);
+    }
+
+    #[test]
+    fn split_widgets_keep_their_own_actions() {
+        let mut doc = form_pdf();
+        let name_id = doc.get_pages()[&1];
+        let name_id = references(doc.get_dictionary(name_id).unwrap().get(b"Annots").unwrap())[0];
+
+        let widget_id = split_widget(&mut doc, name_id).unwrap();
+
+        let field = doc.get_dictionary(name_id).unwrap();
+        assert_eq!(field_name(field), "name");
+        assert!(field.has(b"V") && !field.has(b"Rect") && !field.has(b"P"));
+        let field_actions = field.get(b"AA").and_then(Object::as_dict).unwrap();
+        assert!(field_actions.has(b"K") && !field_actions.has(b"Fo"));
+
+        let widget = doc.get_dictionary(widget_id).unwrap();
+        assert!(widget.has(b"Rect") && widget.has(b"P") && !widget.has(b"T"));
+        let widget_actions = widget.get(b"AA").and_then(Object::as_dict).unwrap();
+        assert!(widget_actions.has(b"Fo") && !widget_actions.has(b"K"));
+
+        // Widgets with a field of their own are left alone
+        let email_widget = references(
+            doc.get_dictionary(doc.get_pages()[&1])
+                .unwrap()
+                .get(b"Annots")
+                .unwrap(),
+        )[1];
+        assert_eq!(split_widget(&mut doc, email_widget), None);
+    }
 }
@@ -1,8 +1,191 @@
 pub mod compress;
+mod forms;
+mod jpeg;
+mod links;
 pub mod merge;
+pub mod optimize;
+mod outline;
+pub mod pages;
+mod placement;
 pub mod protect;
 pub mod rotate;
+pub mod split;
 
 pub use merge::*;
+pub use pages::*;
 pub use protect::*;
 pub use rotate::*;
+pub use split::*;
+
+use crate::error::{SliceError, SliceResult};
+use crate::services::job::{Job, Step};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Creates (or truncates) `path` and returns a buffered writer to hand to the services.
+pub fn create_output(path: &Path) -> SliceResult<BufWriter<File>> {
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|e| SliceError::io(path, e))
+}
+
+/// Runs `write` against a temporary file next to `path` and moves it into place once it
+/// succeeds. On any error, cancellation included, the partial file is deleted and `path`
+/// is left untouched, so the input itself can safely be picked as the destination.
+pub fn write_output<T>(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> SliceResult<T>,
+) -> SliceResult<T> {
+    write_output_file(path, |partial| {
+        create_output(partial).and_then(|mut output| write(&mut output))
+    })
+}
+
+/// Like [`write_output`], for writers that create the file themselves, such as external
+/// tools: `write` gets the path of the temporary file.
+pub fn write_output_file<T>(
+    path: &Path,
+    write: impl FnOnce(&Path) -> SliceResult<T>,
+) -> SliceResult<T> {
+    let partial = partial_path(path);
+    let result = write(&partial);
+
+    match result {
+        Ok(value) => match std::fs::rename(&partial, path) {
+            Ok(()) => Ok(value),
+            Err(e) => {
+                let _ = std::fs::remove_file(&partial);
+                Err(SliceError::io(path, e))
+            }
+        },
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Passwords for encrypted inputs, keyed by the same file ids as the `file_map` they go with.
+pub type Passwords = HashMap<String, String>;
+
+/// Loads the PDF at `path`, decrypting it in memory when a `password` is given. Documents
+/// that still need a password fail with [`SliceError::Encrypted`].
+pub fn load_document(path: &Path, password: Option<&str>) -> SliceResult<Document> {
+    let doc = match password {
+        Some(password) => Document::load_with_password(path, password),
+        None => Document::load(path),
+    }
+    .map_err(|e| SliceError::from_lopdf(path, e))?;
+    if doc.is_encrypted() {
+        return Err(SliceError::Encrypted {
+            path: path.display().to_string(),
+        });
+    }
+    Ok(doc)
+}
+
+/// Serializes `doc` into `output` and flushes it.
+pub fn save_document<W: Write>(doc: &mut Document, output: &mut W, job: &Job) -> SliceResult<()> {
+    job.report(Step::Save, 0, 1, None);
+    job.checkpoint()?;
+    doc.save_to(output)
+        .and_then(|_| output.flush())
+        .map_err(|e| SliceError::internal(format!("Failed to save PDF: {}", e)))?;
+    job.report(Step::Save, 1, 1, None);
+    Ok(())
+}
+
+/// Label for page `page` of `path` in progress events.
+pub(crate) fn page_label(path: &str, page: u32) -> String {
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    format!("{} (page {})", name, page)
+}
+
+/// Page attributes a page can inherit from its ancestors in the page tree.
+const INHERITABLE_PAGE_KEYS: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Copy of the dictionary of page `page_id` with every attribute it inherits through
+/// `/Parent` set on the page itself, so it renders the same once moved under another
+/// `/Pages` node.
+pub(crate) fn resolved_page(doc: &Document, page_id: ObjectId) -> SliceResult<Dictionary> {
+    let mut page = doc
+        .get_dictionary(page_id)
+        .map_err(|e| SliceError::internal(format!("Invalid page {:?}: {}", page_id, e)))?
+        .clone();
+
+    let mut visited = HashSet::from([page_id]);
+    let mut parent = page.get(b"Parent").and_then(Object::as_reference).ok();
+    while let Some(node_id) = parent.filter(|id| visited.insert(*id)) {
+        let Ok(node) = doc.get_dictionary(node_id) else {
+            break;
+        };
+        for key in INHERITABLE_PAGE_KEYS {
+            if !page.has(key) {
+                if let Ok(value) = node.get(key) {
+                    page.set(key, value.clone());
+                }
+            }
+        }
+        parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+    Ok(page)
+}
+
+/// Ids of every object reachable from `roots`, following references through arrays,
+/// dictionaries and stream dictionaries. Objects in `skip` are treated as missing.
+pub(crate) fn reachable_objects<'a>(
+    doc: &Document,
+    roots: impl IntoIterator<Item = &'a Object>,
+    skip: &HashSet<ObjectId>,
+) -> HashSet<ObjectId> {
+    let mut seen = skip.clone();
+    let mut pending: Vec<&Object> = roots.into_iter().collect();
+
+    while let Some(object) = pending.pop() {
+        match object {
+            Object::Reference(id) if seen.insert(*id) => {
+                if let Some(target) = doc.objects.get(id) {
+                    pending.push(target);
+                }
+            }
+            Object::Array(items) => pending.extend(items),
+            Object::Dictionary(dict) => pending.extend(dict.iter().map(|(_, value)| value)),
+            Object::Stream(stream) => pending.extend(stream.dict.iter().map(|(_, value)| value)),
+            _ => {}
+        }
+    }
+
+    seen.retain(|id| !skip.contains(id));
+    seen
+}
+
+/// Decoded content of `stream`, or `None` when one of its filters is not supported.
+pub(crate) fn stream_data(stream: &Stream) -> Option<Vec<u8>> {
+    if stream.dict.has(b"Filter") {
+        stream.decompressed_content().ok()
+    } else {
+        Some(stream.content.clone())
+    }
+}
+
+/// Drops every object the trailer can no longer reach.
+pub(crate) fn retain_reachable(doc: &mut Document) {
+    let reachable = reachable_objects(
+        doc,
+        doc.trailer.iter().map(|(_, value)| value),
+        &HashSet::new(),
+    );
+    doc.objects.retain(|id, _| reachable.contains(id));
+}
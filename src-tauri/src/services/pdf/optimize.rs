@@ -0,0 +1,460 @@
+use super::{load_document, retain_reachable, save_document};
+use crate::error::{SliceError, SliceResult};
+use crate::services::job::{Job, Step};
+use lopdf::{Document, Object, ObjectId, SaveOptions};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::Path;
+
+/// Which structural passes run over a document.
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OptimizeOptions {
+    /// Drop objects nothing refers to any more.
+    pub remove_unused: bool,
+    /// Share one copy of identical streams, fonts and font descriptors.
+    pub deduplicate: bool,
+    /// Drop page thumbnails and the private data editors keep in `/PieceInfo`.
+    pub strip_extras: bool,
+    /// Pack objects into compressed object streams listed by a cross-reference stream,
+    /// which needs PDF 1.5.
+    pub object_streams: bool,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        OptimizeOptions {
+            remove_unused: true,
+            deduplicate: true,
+            strip_extras: true,
+            object_streams: true,
+        }
+    }
+}
+
+#[derive(serde::Serialize, Debug, Default)]
+pub struct OptimizeReport {
+    /// Unreferenced objects dropped.
+    pub removed: usize,
+    /// Objects replaced by an identical one.
+    pub deduplicated: usize,
+    /// Thumbnails and `/PieceInfo` entries dropped.
+    pub stripped: usize,
+}
+
+/// Runs the structural passes `options` asks for over `input_path` and writes the
+/// result to `output`. Page content and images are left as they are.
+pub fn optimize_pdf<W: Write>(
+    input_path: &Path,
+    password: Option<&str>,
+    options: &OptimizeOptions,
+    output: &mut W,
+    job: &Job,
+) -> SliceResult<OptimizeReport> {
+    let mut doc = load_document(input_path, password)?;
+    let report = optimize_document(&mut doc, options);
+    doc.compress();
+    save_optimized(&mut doc, options, output, job)?;
+    Ok(report)
+}
+
+/// Runs the passes of `options` that change the objects of `doc`. Object streams are
+/// left to [`save_optimized`].
+pub(crate) fn optimize_document(doc: &mut Document, options: &OptimizeOptions) -> OptimizeReport {
+    let mut report = OptimizeReport::default();
+    // Stripping first lets the removal pass drop the thumbnail images too
+    if options.strip_extras {
+        report.stripped = strip_extras(doc);
+    }
+    if options.deduplicate {
+        report.deduplicated = deduplicate(doc);
+    }
+    if options.remove_unused {
+        let before = doc.objects.len();
+        retain_reachable(doc);
+        report.removed = before - doc.objects.len();
+    }
+    report
+}
+
+/// Serializes `doc` into `output`, with object streams if `options` asks for them.
+/// Encrypted documents are saved the classic way, as lopdf does not encrypt object
+/// streams.
+pub(crate) fn save_optimized<W: Write>(
+    doc: &mut Document,
+    options: &OptimizeOptions,
+    output: &mut W,
+    job: &Job,
+) -> SliceResult<()> {
+    if !options.object_streams || doc.trailer.has(b"Encrypt") {
+        return save_document(doc, output, job);
+    }
+
+    job.report(Step::Save, 0, 1, None);
+    job.checkpoint()?;
+    let save_options = SaveOptions::builder()
+        .use_object_streams(true)
+        .use_xref_streams(true)
+        .compression_level(9)
+        .build();
+    doc.save_with_options(output, save_options)
+        .and_then(|_| output.flush())
+        .map_err(|e| SliceError::internal(format!("Failed to save PDF: {}", e)))?;
+    job.report(Step::Save, 1, 1, None);
+    Ok(())
+}
+
+/// Drops `/Thumb` from pages and `/PieceInfo` from pages, form XObjects and the catalog.
+/// Returns how many entries went.
+fn strip_extras(doc: &mut Document) -> usize {
+    let mut stripped = 0;
+    for object in doc.objects.values_mut() {
+        let (dict, keys): (_, &[&[u8]]) = match object {
+            Object::Dictionary(dict) => match dict.get(b"Type").and_then(Object::as_name) {
+                Ok(b"Page") => (dict, &[b"Thumb", b"PieceInfo"]),
+                Ok(b"Catalog") => (dict, &[b"PieceInfo"]),
+                _ => continue,
+            },
+            Object::Stream(stream) => match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+                Ok(b"Form") => (&mut stream.dict, &[b"PieceInfo"]),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        for key in keys {
+            if dict.remove(key).is_some() {
+                stripped += 1;
+            }
+        }
+    }
+    stripped
+}
+
+/// Points every reference to a stream, font or font descriptor at the first of the
+/// objects identical to it, until no two are alike. Fonts only become identical once
+/// their font files have been merged, hence the repeats. Returns how many objects were
+/// replaced, which are dropped along the way.
+fn deduplicate(doc: &mut Document) -> usize {
+    let mut deduplicated = 0;
+    loop {
+        let replacements = duplicates(doc);
+        if replacements.is_empty() {
+            return deduplicated;
+        }
+        deduplicated += replacements.len();
+        for object in doc.objects.values_mut() {
+            replace_references(object, &replacements);
+        }
+        for (_, object) in doc.trailer.iter_mut() {
+            replace_references(object, &replacements);
+        }
+        // Nothing refers to the copies now, and leaving them would make them match
+        // again on the next round
+        for id in replacements.keys() {
+            doc.objects.remove(id);
+        }
+    }
+}
+
+/// Map from each object that has an identical twin with a lower id to that twin.
+fn duplicates(doc: &Document) -> HashMap<ObjectId, ObjectId> {
+    let mut candidates: Vec<(&ObjectId, &Object)> = doc
+        .objects
+        .iter()
+        .filter(|(_, object)| shareable(object))
+        .collect();
+    candidates.sort_by_key(|(id, _)| **id);
+
+    let mut seen: HashMap<u64, Vec<ObjectId>> = HashMap::new();
+    let mut replacements = HashMap::new();
+    for (&id, object) in candidates {
+        let mut hasher = DefaultHasher::new();
+        hash_object(object, &mut hasher);
+        let twins = seen.entry(hasher.finish()).or_default();
+        let original = twins.iter().find(|&&twin| {
+            doc.objects
+                .get(&twin)
+                .is_some_and(|twin| same(twin, object))
+        });
+        match original {
+            Some(&original) => {
+                replacements.insert(id, original);
+            }
+            None => twins.push(id),
+        }
+    }
+    replacements
+}
+
+/// Whether an object may be shared by everything that refers to an identical one.
+/// Pages, annotations and fields keep their identity, so only streams, fonts and font
+/// descriptors are.
+fn shareable(object: &Object) -> bool {
+    match object {
+        Object::Stream(stream) => !matches!(
+            stream.dict.get(b"Type").and_then(Object::as_name),
+            Ok(b"ObjStm" | b"XRef")
+        ),
+        Object::Dictionary(dict) => matches!(
+            dict.get(b"Type").and_then(Object::as_name),
+            Ok(b"Font" | b"FontDescriptor")
+        ),
+        _ => false,
+    }
+}
+
+/// Equality that ignores where a stream was read from.
+fn same(a: &Object, b: &Object) -> bool {
+    match (a, b) {
+        (Object::Stream(a), Object::Stream(b)) => a.dict == b.dict && a.content == b.content,
+        _ => a == b,
+    }
+}
+
+/// Hashes `object` so that objects [`same`] considers equal hash alike. Dictionary
+/// entries are hashed in key order, as their order does not matter.
+fn hash_object<H: Hasher>(object: &Object, state: &mut H) {
+    std::mem::discriminant(object).hash(state);
+    match object {
+        Object::Null => {}
+        Object::Boolean(value) => value.hash(state),
+        Object::Integer(value) => value.hash(state),
+        Object::Real(value) => value.to_bits().hash(state),
+        Object::Name(name) => name.hash(state),
+        Object::String(bytes, _) => bytes.hash(state),
+        Object::Array(items) => {
+            items.len().hash(state);
+            for item in items {
+                hash_object(item, state);
+            }
+        }
+        Object::Dictionary(dict) => hash_dictionary(dict, state),
+        Object::Stream(stream) => {
+            hash_dictionary(&stream.dict, state);
+            stream.content.hash(state);
+        }
+        Object::Reference(id) => id.hash(state),
+    }
+}
+
+fn hash_dictionary<H: Hasher>(dict: &lopdf::Dictionary, state: &mut H) {
+    let mut entries: Vec<(&Vec<u8>, &Object)> = dict.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    entries.len().hash(state);
+    for (key, value) in entries {
+        key.hash(state);
+        hash_object(value, state);
+    }
+}
+
+fn replace_references(object: &mut Object, replacements: &HashMap<ObjectId, ObjectId>) {
+    match object {
+        Object::Reference(id) => {
+            if let Some(&original) = replacements.get(id) {
+                *id = original;
+            }
+        }
+        Object::Array(items) => {
+            for item in items {
+                replace_references(item, replacements);
+            }
+        }
+        Object::Dictionary(dict) => {
+            for (_, value) in dict.iter_mut() {
+                replace_references(value, replacements);
+            }
+        }
+        Object::Stream(stream) => {
+            for (_, value) in stream.dict.iter_mut() {
+                replace_references(value, replacements);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Dictionary, Stream};
+
+    /// The ids of the objects the test document is made of.
+    struct Ids {
+        pages: [ObjectId; 2],
+        thumb: ObjectId,
+        unused: ObjectId,
+    }
+
+    /// A 1.4 document of two pages that each draw their own copy of the same image with
+    /// their own copy of the same embedded font. The first page has a thumbnail and
+    /// `/PieceInfo`, as does the catalog, and one object is not referenced at all.
+    fn duplicated_pdf() -> (Document, Ids) {
+        let mut doc = Document::with_version("1.4");
+        let pages_id = doc.new_object_id();
+        let mut pages = Vec::new();
+        for number in 1..=2 {
+            let image_id = doc.add_object(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => 2,
+                    "Height" => 1,
+                    "ColorSpace" => "DeviceGray",
+                    "BitsPerComponent" => 8,
+                },
+                vec![0, 255],
+            ));
+            let font_file_id =
+                doc.add_object(Stream::new(dictionary! {}, b"font program".to_vec()));
+            let descriptor_id = doc.add_object(dictionary! {
+                "Type" => "FontDescriptor",
+                "FontName" => "Embedded",
+                "FontFile2" => font_file_id,
+            });
+            let font_id = doc.add_object(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "TrueType",
+                "BaseFont" => "Embedded",
+                "FontDescriptor" => descriptor_id,
+            });
+            let content = format!(
+                "q 72 0 0 36 0 0 cm /Im1 Do Q BT /F1 12 Tf ({}) Tj ET",
+                number
+            );
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.into_bytes()));
+            pages.push(doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+                "Contents" => content_id,
+                "Resources" => dictionary! {
+                    "Font" => dictionary! { "F1" => font_id },
+                    "XObject" => dictionary! { "Im1" => image_id },
+                },
+            }));
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => 2,
+                "Kids" => pages.iter().map(|&id| id.into()).collect::<Vec<Object>>(),
+            }),
+        );
+
+        let thumb = doc.add_object(Stream::new(
+            dictionary! {
+                "Width" => 1,
+                "Height" => 1,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![128],
+        ));
+        let piece_info = dictionary! {
+            "Editor" => dictionary! { "Private" => Object::string_literal("state") },
+        };
+        let page = doc.get_dictionary_mut(pages[0]).unwrap();
+        page.set("Thumb", thumb);
+        page.set("PieceInfo", piece_info.clone());
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "PieceInfo" => piece_info,
+        });
+        doc.trailer.set("Root", catalog_id);
+        let unused = doc.add_object(dictionary! { "Orphan" => true });
+
+        let ids = Ids {
+            pages: [pages[0], pages[1]],
+            thumb,
+            unused,
+        };
+        (doc, ids)
+    }
+
+    /// Id of the `key` resource named `name` on page `page_id`.
+    fn resource(doc: &Document, page_id: ObjectId, key: &[u8], name: &[u8]) -> ObjectId {
+        doc.get_dictionary(page_id)
+            .and_then(|page| page.get(b"Resources"))
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(key))
+            .and_then(Object::as_dict)
+            .and_then(|named| named.get(name))
+            .and_then(Object::as_reference)
+            .unwrap()
+    }
+
+    fn dict(doc: &Document, id: ObjectId) -> &Dictionary {
+        doc.get_dictionary(id).unwrap()
+    }
+
+    #[test]
+    fn identical_images_and_fonts_are_shared() {
+        let (mut doc, ids) = duplicated_pdf();
+        let report = optimize_document(&mut doc, &OptimizeOptions::default());
+
+        // The image, and the font along with its descriptor and font file
+        assert_eq!(report.deduplicated, 4);
+        let [first, second] = ids.pages;
+        let image = resource(&doc, first, b"XObject", b"Im1");
+        assert_eq!(resource(&doc, second, b"XObject", b"Im1"), image);
+        assert!(doc.get_object(image).is_ok());
+        let font = resource(&doc, first, b"Font", b"F1");
+        assert_eq!(resource(&doc, second, b"Font", b"F1"), font);
+        let descriptor = dict(&doc, font)
+            .get(b"FontDescriptor")
+            .and_then(Object::as_reference)
+            .unwrap();
+        let font_file = dict(&doc, descriptor)
+            .get(b"FontFile2")
+            .and_then(Object::as_reference)
+            .unwrap();
+        assert!(doc.get_object(font_file).is_ok());
+        // Everything left is told apart from everything else
+        assert!(duplicates(&doc).is_empty());
+    }
+
+    #[test]
+    fn thumbnails_and_piece_info_are_stripped() {
+        let (mut doc, ids) = duplicated_pdf();
+        let report = optimize_document(&mut doc, &OptimizeOptions::default());
+
+        assert_eq!(report.stripped, 3);
+        let page = dict(&doc, ids.pages[0]);
+        assert!(!page.has(b"Thumb") && !page.has(b"PieceInfo"));
+        assert!(!doc.catalog().unwrap().has(b"PieceInfo"));
+        assert!(!doc.objects.contains_key(&ids.thumb));
+    }
+
+    #[test]
+    fn unreferenced_objects_are_removed() {
+        let (mut doc, ids) = duplicated_pdf();
+        let options = OptimizeOptions {
+            deduplicate: false,
+            strip_extras: false,
+            ..OptimizeOptions::default()
+        };
+        let report = optimize_document(&mut doc, &options);
+
+        assert_eq!(report.removed, 1);
+        assert!(!doc.objects.contains_key(&ids.unused));
+        assert!(doc.objects.contains_key(&ids.thumb));
+    }
+
+    #[test]
+    fn object_streams_reload_as_pdf_1_5() {
+        let (mut doc, _) = duplicated_pdf();
+        let options = OptimizeOptions::default();
+        optimize_document(&mut doc, &options);
+        let mut output = Vec::new();
+        save_optimized(&mut doc, &options, &mut output, &Job::silent()).unwrap();
+
+        let reloaded = Document::load_mem(&output).unwrap();
+        assert!(reloaded.version.as_str() >= "1.5", "{}", reloaded.version);
+        assert_eq!(reloaded.get_pages().len(), 2);
+        assert!(output.windows(7).any(|window| window == b"/ObjStm"));
+    }
+}
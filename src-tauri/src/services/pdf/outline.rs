@@ -0,0 +1,445 @@
+use lopdf::{decode_text_string, dictionary, text_string, Dictionary, Document, Object, ObjectId};
+use std::collections::HashSet;
+
+/// Destinations can point at named destinations, which can point at destination
+/// dictionaries, and so on. Broken files sometimes loop, so give up after a few hops.
+const MAX_DESTINATION_HOPS: usize = 8;
+
+/// Name trees are shallow in practice, anything deeper is a loop.
+const MAX_NAME_TREE_DEPTH: usize = 32;
+
+/// A bookmark as found in the document's `/Outlines` tree.
+pub(crate) struct OutlineItem {
+    pub title: String,
+    /// Page the bookmark jumps to, when it points inside this document.
+    pub page: Option<ObjectId>,
+}
+
+/// A bookmark to write into a document with [`write_outline`].
+pub(crate) struct Bookmark {
+    pub title: Object,
+    /// Explicit destination, its first element being the target page.
+    pub dest: Vec<Object>,
+    pub open: bool,
+    /// `/C` (color) and `/F` (bold, italic) of the original item.
+    pub style: Vec<(Vec<u8>, Object)>,
+    pub children: Vec<Bookmark>,
+}
+
+/// Key of a named destination: a name in the catalog's `/Dests` dictionary (PDF 1.1) or
+/// a string in the `/Dests` name tree.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub(crate) enum DestinationName {
+    Name(Vec<u8>),
+    String(Vec<u8>),
+}
+
+impl DestinationName {
+    pub fn from_object(object: &Object) -> Option<Self> {
+        match object {
+            Object::Name(key) => Some(DestinationName::Name(key.clone())),
+            Object::String(key, _) => Some(DestinationName::String(key.clone())),
+            _ => None,
+        }
+    }
+
+    /// The same kind of key with `_{number}` appended.
+    pub fn numbered(&self, number: usize) -> Self {
+        let suffix = format!("_{}", number).into_bytes();
+        match self {
+            DestinationName::Name(key) => DestinationName::Name([key, &suffix[..]].concat()),
+            DestinationName::String(key) => DestinationName::String([key, &suffix[..]].concat()),
+        }
+    }
+
+    pub fn to_object(&self) -> Object {
+        match self {
+            DestinationName::Name(key) => Object::Name(key.clone()),
+            DestinationName::String(key) => Object::string_literal(key.clone()),
+        }
+    }
+}
+
+impl Bookmark {
+    /// Closed bookmark titled `title` that shows the whole of `page`.
+    pub fn new(title: &str, page: ObjectId, children: Vec<Bookmark>) -> Self {
+        Bookmark {
+            title: text_string(title),
+            dest: vec![page.into(), "Fit".into()],
+            open: false,
+            style: Vec::new(),
+            children,
+        }
+    }
+}
+
+/// Top-level entries of the outline, in order. Empty when the document has none.
+pub(crate) fn top_level_outline(doc: &Document) -> Vec<OutlineItem> {
+    let Some(root) = outline_root(doc) else {
+        return Vec::new();
+    };
+    outline_item_ids(doc, root, &mut HashSet::new())
+        .into_iter()
+        .filter_map(|id| {
+            let item = doc.get_dictionary(id).ok()?;
+            Some(OutlineItem {
+                title: item
+                    .get(b"Title")
+                    .ok()
+                    .and_then(|title| doc.dereference(title).ok())
+                    .and_then(|(_, title)| decode_text_string(title).ok())
+                    .unwrap_or_default(),
+                page: outline_target(doc, item),
+            })
+        })
+        .collect()
+}
+
+/// The whole outline of `doc`, with each target page passed through `remap`. Items whose
+/// page `remap` drops are left out, unless some of their children survive; they then
+/// point at their first remaining child.
+pub(crate) fn read_outline(
+    doc: &Document,
+    remap: &dyn Fn(ObjectId) -> Option<ObjectId>,
+) -> Vec<Bookmark> {
+    match outline_root(doc) {
+        Some(root) => read_bookmarks(doc, root, remap, &mut HashSet::new()),
+        None => Vec::new(),
+    }
+}
+
+fn read_bookmarks(
+    doc: &Document,
+    parent: &Dictionary,
+    remap: &dyn Fn(ObjectId) -> Option<ObjectId>,
+    seen: &mut HashSet<ObjectId>,
+) -> Vec<Bookmark> {
+    let mut bookmarks = Vec::new();
+    for id in outline_item_ids(doc, parent, seen) {
+        let Ok(item) = doc.get_dictionary(id) else {
+            continue;
+        };
+        let children = read_bookmarks(doc, item, remap, seen);
+        let dest = item_destination(doc, item).and_then(|dest| {
+            let page = remap(dest.first()?.as_reference().ok()?)?;
+            Some(remap_destination(dest, page))
+        });
+        let Some(dest) = dest.or_else(|| children.first().map(|child| child.dest.clone())) else {
+            continue;
+        };
+
+        bookmarks.push(Bookmark {
+            title: item
+                .get(b"Title")
+                .ok()
+                .and_then(|title| doc.dereference(title).ok())
+                .map(|(_, title)| title.clone())
+                .unwrap_or_else(|| text_string("")),
+            dest,
+            open: item.get(b"Count").and_then(Object::as_i64).unwrap_or(0) > 0,
+            style: [b"C".as_slice(), b"F".as_slice()]
+                .into_iter()
+                .filter_map(|key| Some((key.to_vec(), style_value(doc, item.get(key).ok()?)?)))
+                .collect(),
+            children,
+        });
+    }
+    bookmarks
+}
+
+/// `/C` or `/F` of an outline item, with references into `doc` resolved so the value
+/// can be written into another document.
+fn style_value(doc: &Document, value: &Object) -> Option<Object> {
+    match doc.dereference(value).ok()?.1 {
+        Object::Array(items) => items
+            .iter()
+            .map(|item| doc.dereference(item).ok().map(|(_, item)| item.clone()))
+            .collect::<Option<Vec<_>>>()
+            .map(Object::Array),
+        value => Some(value.clone()),
+    }
+}
+
+/// Writes `bookmarks` as the items of a new outline and returns the id of its root, to be
+/// set as `/Outlines` in the catalog. Returns `None` when there is nothing to write.
+pub(crate) fn write_outline(doc: &mut Document, bookmarks: Vec<Bookmark>) -> Option<ObjectId> {
+    if bookmarks.is_empty() {
+        return None;
+    }
+    let root_id = doc.new_object_id();
+    let (first, last, visible) = write_bookmarks(doc, root_id, bookmarks);
+    doc.objects.insert(
+        root_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => first,
+            "Last" => last,
+            "Count" => visible,
+        }),
+    );
+    Some(root_id)
+}
+
+/// Writes `bookmarks` as siblings under `parent`. Returns the first and last ids and how
+/// many items show when `parent` is open.
+fn write_bookmarks(
+    doc: &mut Document,
+    parent: ObjectId,
+    bookmarks: Vec<Bookmark>,
+) -> (ObjectId, ObjectId, i64) {
+    let ids: Vec<ObjectId> = bookmarks.iter().map(|_| doc.new_object_id()).collect();
+    let mut visible = 0;
+
+    for (index, bookmark) in bookmarks.into_iter().enumerate() {
+        let mut item = dictionary! {
+            "Title" => bookmark.title,
+            "Parent" => parent,
+            "Dest" => bookmark.dest,
+        };
+        for (key, value) in bookmark.style {
+            item.set(key, value);
+        }
+        if index > 0 {
+            item.set("Prev", ids[index - 1]);
+        }
+        if let Some(&next) = ids.get(index + 1) {
+            item.set("Next", next);
+        }
+        if !bookmark.children.is_empty() {
+            let (first, last, shown) = write_bookmarks(doc, ids[index], bookmark.children);
+            item.set("First", first);
+            item.set("Last", last);
+            // A negative count marks a closed item.
+            item.set("Count", if bookmark.open { shown } else { -shown });
+            if bookmark.open {
+                visible += shown;
+            }
+        }
+        visible += 1;
+        doc.objects.insert(ids[index], Object::Dictionary(item));
+    }
+
+    (ids[0], ids[ids.len() - 1], visible)
+}
+
+fn outline_root(doc: &Document) -> Option<&Dictionary> {
+    let outlines = doc.catalog().ok()?.get(b"Outlines").ok()?;
+    dictionary(doc, outlines)
+}
+
+/// Ids of the children of the outline node `parent`, following `/First` and then
+/// `/Next`. Items already in `seen` end the chain, which guards against loops.
+fn outline_item_ids(
+    doc: &Document,
+    parent: &Dictionary,
+    seen: &mut HashSet<ObjectId>,
+) -> Vec<ObjectId> {
+    let mut ids = Vec::new();
+    let mut next = parent.get(b"First").and_then(Object::as_reference).ok();
+
+    while let Some(id) = next.filter(|id| seen.insert(*id)) {
+        let Ok(item) = doc.get_dictionary(id) else {
+            break;
+        };
+        ids.push(id);
+        next = item.get(b"Next").and_then(Object::as_reference).ok();
+    }
+    ids
+}
+
+/// Page an outline item or link annotation jumps to, through `/Dest` or a `/GoTo` action.
+pub(crate) fn outline_target(doc: &Document, item: &Dictionary) -> Option<ObjectId> {
+    item_destination(doc, item)?.first()?.as_reference().ok()
+}
+
+/// Explicit destination of an outline item or link annotation, through `/Dest` or a
+/// `/GoTo` action.
+pub(crate) fn item_destination<'a>(
+    doc: &'a Document,
+    item: &'a Dictionary,
+) -> Option<&'a [Object]> {
+    explicit_destination(doc, raw_destination(doc, item)?)
+}
+
+/// The `/Dest` of an outline item or link annotation, or the `/D` of its `/GoTo` action,
+/// as written: an explicit destination or the name of one.
+pub(crate) fn raw_destination<'a>(doc: &'a Document, item: &'a Dictionary) -> Option<&'a Object> {
+    if let Ok(dest) = item.get(b"Dest") {
+        return Some(dest);
+    }
+    let action = dictionary(doc, item.get(b"A").ok()?)?;
+    if action.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+        return None;
+    }
+    action.get(b"D").ok()
+}
+
+/// The `[page /View ...]` array an explicit or named destination comes down to.
+pub(crate) fn explicit_destination<'a>(
+    doc: &'a Document,
+    dest: &'a Object,
+) -> Option<&'a [Object]> {
+    let mut dest = dest;
+    for _ in 0..MAX_DESTINATION_HOPS {
+        dest = match doc.dereference(dest).ok()?.1 {
+            Object::Array(items) => return Some(items),
+            Object::Dictionary(dict) => dict.get(b"D").ok()?,
+            name @ (Object::Name(_) | Object::String(..)) => named_destination(doc, name)?,
+            _ => return None,
+        };
+    }
+    None
+}
+
+/// `dest` pointed at `page` instead, keeping its view.
+pub(crate) fn remap_destination(dest: &[Object], page: ObjectId) -> Vec<Object> {
+    let mut remapped = dest.to_vec();
+    match remapped.first_mut() {
+        Some(target) => *target = page.into(),
+        None => remapped.push(page.into()),
+    }
+    if remapped.len() == 1 {
+        remapped.push("Fit".into());
+    }
+    remapped
+}
+
+/// Looks `name` up in the catalog's `/Dests` dictionary (PDF 1.1) or the `/Dests`
+/// name tree under `/Names`.
+pub(crate) fn named_destination<'a>(doc: &'a Document, name: &Object) -> Option<&'a Object> {
+    let catalog = doc.catalog().ok()?;
+    match name {
+        Object::Name(key) => dictionary(doc, catalog.get(b"Dests").ok()?)?.get(key).ok(),
+        Object::String(key, _) => {
+            let names = dictionary(doc, catalog.get(b"Names").ok()?)?;
+            let tree = dictionary(doc, names.get(b"Dests").ok()?)?;
+            find_in_name_tree(doc, tree, key, 0)
+        }
+        _ => None,
+    }
+}
+
+/// Every named destination of `doc`, from both the catalog's `/Dests` dictionary and the
+/// `/Dests` name tree, with its value as written.
+pub(crate) fn named_destinations(doc: &Document) -> Vec<(DestinationName, &Object)> {
+    let Ok(catalog) = doc.catalog() else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    if let Some(dests) = catalog.get(b"Dests").ok().and_then(|d| dictionary(doc, d)) {
+        found.extend(
+            dests
+                .iter()
+                .map(|(key, value)| (DestinationName::Name(key.clone()), value)),
+        );
+    }
+    let tree = catalog
+        .get(b"Names")
+        .ok()
+        .and_then(|names| dictionary(doc, names))
+        .and_then(|names| dictionary(doc, names.get(b"Dests").ok()?));
+    if let Some(tree) = tree {
+        name_tree_entries(doc, tree, 0, &mut found);
+    }
+    found
+}
+
+fn name_tree_entries<'a>(
+    doc: &'a Document,
+    node: &'a Dictionary,
+    depth: usize,
+    found: &mut Vec<(DestinationName, &'a Object)>,
+) {
+    if depth > MAX_NAME_TREE_DEPTH {
+        return;
+    }
+    if let Ok(names) = node.get(b"Names").and_then(Object::as_array) {
+        for pair in names.chunks_exact(2) {
+            if let Ok(key) = pair[0].as_str() {
+                found.push((DestinationName::String(key.to_vec()), &pair[1]));
+            }
+        }
+    }
+    if let Ok(kids) = node.get(b"Kids").and_then(Object::as_array) {
+        for kid in kids.iter().filter_map(|kid| dictionary(doc, kid)) {
+            name_tree_entries(doc, kid, depth + 1, found);
+        }
+    }
+}
+
+fn find_in_name_tree<'a>(
+    doc: &'a Document,
+    node: &'a Dictionary,
+    key: &[u8],
+    depth: usize,
+) -> Option<&'a Object> {
+    if depth > MAX_NAME_TREE_DEPTH {
+        return None;
+    }
+    if let Ok(names) = node.get(b"Names").and_then(Object::as_array) {
+        let found = names
+            .chunks_exact(2)
+            .find(|pair| pair[0].as_str().is_ok_and(|name| name == key));
+        if let Some(pair) = found {
+            return Some(&pair[1]);
+        }
+    }
+    let kids = node.get(b"Kids").and_then(Object::as_array).ok()?;
+    kids.iter()
+        .filter_map(|kid| dictionary(doc, kid))
+        .find_map(|kid| find_in_name_tree(doc, kid, key, depth + 1))
+}
+
+fn dictionary<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Dictionary> {
+    doc.dereference(object).ok()?.1.as_dict().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styles_are_read_as_direct_objects() {
+        let mut doc = Document::with_version("1.7");
+        let page_id = doc.add_object(dictionary! { "Type" => "Page" });
+        let red = doc.add_object(Object::Real(1.0));
+        let color_id = doc.add_object(vec![red.into(), 0.into(), 0.into()]);
+        let flags_id = doc.add_object(2);
+        let outlines_id = doc.new_object_id();
+        let item_id = doc.add_object(dictionary! {
+            "Title" => text_string("Chapter"),
+            "Parent" => outlines_id,
+            "Dest" => vec![page_id.into(), "Fit".into()],
+            "C" => color_id,
+            "F" => flags_id,
+        });
+        doc.objects.insert(
+            outlines_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Outlines",
+                "First" => item_id,
+                "Last" => item_id,
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Outlines" => outlines_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+
+        let bookmarks = read_outline(&doc, &Some);
+
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(
+            bookmarks[0].style,
+            [
+                (
+                    b"C".to_vec(),
+                    Object::Array(vec![1.0.into(), 0.into(), 0.into()])
+                ),
+                (b"F".to_vec(), Object::Integer(2)),
+            ]
+        );
+    }
+}
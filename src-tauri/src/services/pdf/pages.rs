@@ -0,0 +1,589 @@
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::{DynamicImage, GenericImageView};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use super::jpeg::{jpeg_image, jpeg_info};
+use crate::error::{SliceError, SliceResult};
+
+/// Largest page side PDF viewers are required to handle, in points (200 inches).
+const MAX_PAGE_SIDE: f32 = 14_400.0;
+
+/// Font size of the text on separator pages, in points.
+const TEXT_SIZE: f32 = 24.0;
+
+/// Margin between the text of a separator page and the page edge, in points.
+const TEXT_MARGIN: f32 = 72.0;
+
+/// Size of a generated page, in points (1/72 inch).
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PageSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl PageSize {
+    pub const A4: PageSize = PageSize {
+        width: 595.0,
+        height: 842.0,
+    };
+    pub const A3: PageSize = PageSize {
+        width: 842.0,
+        height: 1191.0,
+    };
+    pub const LETTER: PageSize = PageSize {
+        width: 612.0,
+        height: 792.0,
+    };
+    pub const LEGAL: PageSize = PageSize {
+        width: 612.0,
+        height: 1008.0,
+    };
+
+    pub(crate) fn validate(&self) -> SliceResult<()> {
+        let valid = |side: f32| side.is_finite() && (1.0..=MAX_PAGE_SIDE).contains(&side);
+        if valid(self.width) && valid(self.height) {
+            Ok(())
+        } else {
+            Err(SliceError::invalid(format!(
+                "Page size must be between 1 and {} points, got {} x {}",
+                MAX_PAGE_SIDE, self.width, self.height
+            )))
+        }
+    }
+
+    fn landscape(self) -> Self {
+        PageSize {
+            width: self.width.max(self.height),
+            height: self.width.min(self.height),
+        }
+    }
+
+    fn portrait(self) -> Self {
+        PageSize {
+            width: self.width.min(self.height),
+            height: self.width.max(self.height),
+        }
+    }
+}
+
+impl Default for PageSize {
+    fn default() -> Self {
+        PageSize::A4
+    }
+}
+
+/// Paper of the pages made from images.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+    Legal,
+    A3,
+    Custom {
+        width: f32,
+        height: f32,
+    },
+    /// A page the size of the image at 72 pixels per inch, plus the margins.
+    SameAsImage,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Orientation {
+    /// Landscape for images wider than they are tall, portrait otherwise.
+    #[default]
+    Auto,
+    Portrait,
+    Landscape,
+}
+
+/// How an image fills the space inside the margins.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum FitMode {
+    /// The whole image, as large as it fits, centered.
+    #[default]
+    Fit,
+    /// The whole space, cropping the edges of the image that stick out.
+    Fill,
+    /// The whole space, distorting the image.
+    Stretch,
+}
+
+/// Layout of the pages made from images in a merge.
+#[derive(serde::Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImagePageOptions {
+    pub paper: PaperSize,
+    /// Ignored with [`PaperSize::SameAsImage`].
+    pub orientation: Orientation,
+    /// Space left empty on every side of the image, in points.
+    pub margin: f32,
+    pub fit: FitMode,
+    /// Images are scaled down to at most this many pixels per inch of the page.
+    pub max_dpi: f32,
+}
+
+impl Default for ImagePageOptions {
+    fn default() -> Self {
+        ImagePageOptions {
+            paper: PaperSize::A4,
+            orientation: Orientation::Auto,
+            margin: 0.0,
+            fit: FitMode::Fit,
+            max_dpi: 150.0,
+        }
+    }
+}
+
+impl ImagePageOptions {
+    pub(crate) fn validate(&self) -> SliceResult<()> {
+        if !self.max_dpi.is_finite() || self.max_dpi < 1.0 {
+            return Err(SliceError::invalid(format!(
+                "Maximum DPI must be at least 1, got {}",
+                self.max_dpi
+            )));
+        }
+        if !self.margin.is_finite() || self.margin < 0.0 {
+            return Err(SliceError::invalid(format!(
+                "Margins cannot be negative, got {}",
+                self.margin
+            )));
+        }
+        if let Some(paper) = self.paper_size() {
+            paper.validate()?;
+            if 2.0 * self.margin >= paper.width.min(paper.height) {
+                return Err(SliceError::invalid(format!(
+                    "Margins of {} points leave no room on a {} x {} page",
+                    self.margin, paper.width, paper.height
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Portrait size of the paper, `None` when it follows the image.
+    fn paper_size(&self) -> Option<PageSize> {
+        let size = match self.paper {
+            PaperSize::A4 => PageSize::A4,
+            PaperSize::Letter => PageSize::LETTER,
+            PaperSize::Legal => PageSize::LEGAL,
+            PaperSize::A3 => PageSize::A3,
+            PaperSize::Custom { width, height } => PageSize { width, height },
+            PaperSize::SameAsImage => return None,
+        };
+        Some(size)
+    }
+
+    /// Size of the page for an image of `width` by `height` pixels.
+    fn page_size(&self, width: u32, height: u32) -> PageSize {
+        let Some(paper) = self.paper_size() else {
+            // One pixel per point, shrunk when the page would get too large
+            let scale = (MAX_PAGE_SIDE - 2.0 * self.margin) / width.max(height) as f32;
+            let scale = scale.min(1.0);
+            return PageSize {
+                width: width as f32 * scale + 2.0 * self.margin,
+                height: height as f32 * scale + 2.0 * self.margin,
+            };
+        };
+        match self.orientation {
+            Orientation::Auto if width > height => paper.landscape(),
+            Orientation::Auto | Orientation::Portrait => paper.portrait(),
+            Orientation::Landscape => paper.landscape(),
+        }
+    }
+}
+
+/// Adds a page showing the image at `path`, laid out as `options` say, and returns its
+/// id. JPEG files that need no cropping or scaling are embedded as they are.
+pub(crate) fn image_page(
+    doc: &mut Document,
+    path: &Path,
+    options: &ImagePageOptions,
+) -> SliceResult<ObjectId> {
+    let data = fs::read(path).map_err(|e| SliceError::io(path, e))?;
+    let decode =
+        |data: &[u8]| image::load_from_memory(data).map_err(|e| SliceError::from_image(path, e));
+
+    // A JPEG header is enough to lay the page out
+    let jpeg = jpeg_info(&data);
+    let (img, (width, height)) = match &jpeg {
+        Some(info) => (None, (info.width, info.height)),
+        None => {
+            let img = decode(&data)?;
+            let dimensions = img.dimensions();
+            (Some(img), dimensions)
+        }
+    };
+    let layout = ImageLayout::new(options, width, height);
+
+    let image = match jpeg {
+        Some(info) if layout.keeps_pixels() => jpeg_image(data, &info),
+        _ => {
+            let img = match img {
+                Some(img) => img,
+                None => decode(&data)?,
+            };
+            flate_image(doc, layout.apply(img))?
+        }
+    };
+    let img_obj_id = doc.add_object(image);
+
+    let content = format!(
+        "q {} 0 0 {} {} {} cm /Im1 Do Q",
+        layout.width, layout.height, layout.x, layout.y
+    );
+    let resources = dictionary! {
+        "XObject" => dictionary! { "Im1" => img_obj_id }
+    };
+    Ok(add_page(doc, layout.page, content.into_bytes(), resources))
+}
+
+/// Where an image goes on its page, and which of its pixels end up in the PDF.
+struct ImageLayout {
+    page: PageSize,
+    /// Lower-left corner and size of the image on the page, in points.
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    /// Pixel size of the original image.
+    original: (u32, u32),
+    /// Part of the image that shows: left, top, width and height in pixels.
+    crop: (u32, u32, u32, u32),
+    /// Pixel size the part that shows is scaled to.
+    pixels: (u32, u32),
+}
+
+impl ImageLayout {
+    fn new(options: &ImagePageOptions, width: u32, height: u32) -> Self {
+        let page = options.page_size(width, height);
+
+        // Space inside the margins
+        let box_width = page.width - 2.0 * options.margin;
+        let box_height = page.height - 2.0 * options.margin;
+        let fit_scale = (box_width / width as f32).min(box_height / height as f32);
+        let fill_scale = (box_width / width as f32).max(box_height / height as f32);
+
+        let full = (0, 0, width, height);
+        let (draw_width, draw_height, crop) = match options.fit {
+            FitMode::Fit => (width as f32 * fit_scale, height as f32 * fit_scale, full),
+            FitMode::Fill => {
+                let visible_width = ((box_width / fill_scale).round() as u32).clamp(1, width);
+                let visible_height = ((box_height / fill_scale).round() as u32).clamp(1, height);
+                let crop = (
+                    (width - visible_width) / 2,
+                    (height - visible_height) / 2,
+                    visible_width,
+                    visible_height,
+                );
+                (box_width, box_height, crop)
+            }
+            FitMode::Stretch => (box_width, box_height, full),
+        };
+
+        // No more than `max_dpi` pixels per inch in either direction
+        let pixels = (
+            ((draw_width / 72.0 * options.max_dpi).round() as u32).clamp(1, crop.2),
+            ((draw_height / 72.0 * options.max_dpi).round() as u32).clamp(1, crop.3),
+        );
+
+        ImageLayout {
+            page,
+            x: (page.width - draw_width) / 2.0,
+            y: (page.height - draw_height) / 2.0,
+            width: draw_width,
+            height: draw_height,
+            original: (width, height),
+            crop,
+            pixels,
+        }
+    }
+
+    /// Whether the image goes into the PDF pixel for pixel.
+    fn keeps_pixels(&self) -> bool {
+        self.crop == (0, 0, self.original.0, self.original.1) && self.pixels == self.original
+    }
+
+    /// `img` cropped and scaled as laid out.
+    fn apply(&self, img: DynamicImage) -> DynamicImage {
+        let (left, top, width, height) = self.crop;
+        let img = if (width, height) == self.original {
+            img
+        } else {
+            img.crop_imm(left, top, width, height)
+        };
+        if self.pixels == (width, height) {
+            img
+        } else {
+            img.resize_exact(
+                self.pixels.0,
+                self.pixels.1,
+                image::imageops::FilterType::Lanczos3,
+            )
+        }
+    }
+}
+
+/// Image XObject holding the pixels of `img` as Flate-compressed RGB. An alpha channel
+/// goes into a separate `/SMask` image added to `doc`, unless every pixel is opaque.
+fn flate_image(doc: &mut Document, img: DynamicImage) -> SliceResult<Stream> {
+    let (width, height) = img.dimensions();
+    if !img.color().has_alpha() {
+        return flate_stream(img.to_rgb8().as_raw(), width, height, "DeviceRGB");
+    }
+
+    let rgba = img.to_rgba8();
+    let alpha: Vec<u8> = rgba.pixels().map(|pixel| pixel[3]).collect();
+    let rgb: Vec<u8> = rgba
+        .pixels()
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    let mut image = flate_stream(&rgb, width, height, "DeviceRGB")?;
+    if alpha.iter().any(|&a| a < u8::MAX) {
+        let mask_id = doc.add_object(flate_stream(&alpha, width, height, "DeviceGray")?);
+        image.dict.set("SMask", mask_id);
+    }
+    Ok(image)
+}
+
+/// Image XObject of `width` by `height` 8-bit `samples` in `color_space`, compressed
+/// with Flate.
+fn flate_stream(samples: &[u8], width: u32, height: u32, color_space: &str) -> SliceResult<Stream> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(samples)
+        .map_err(|e| SliceError::internal(e.to_string()))?;
+    let compressed_data = encoder
+        .finish()
+        .map_err(|e| SliceError::internal(e.to_string()))?;
+
+    Ok(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => width as i64,
+            "Height" => height as i64,
+            "ColorSpace" => color_space,
+            "BitsPerComponent" => 8,
+            "Filter" => "FlateDecode",
+        },
+        compressed_data,
+    ))
+}
+
+/// Adds an empty page of the given size and returns its id.
+pub(crate) fn blank_page(doc: &mut Document, size: PageSize) -> ObjectId {
+    add_page(doc, size, Vec::new(), Dictionary::new())
+}
+
+/// Adds a page showing `text` in Helvetica, one line per line of `text`, and returns its
+/// id. The block of lines is centered vertically. Characters outside Latin-1 show as `?`,
+/// which is all the standard fonts can draw without embedding one.
+pub(crate) fn text_page(doc: &mut Document, text: &str, size: PageSize) -> ObjectId {
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+
+    let lines: Vec<&str> = text.lines().collect();
+    let leading = TEXT_SIZE * 1.4;
+    let block_height = leading * lines.len().saturating_sub(1) as f32;
+    let top = (size.height + block_height) / 2.0;
+
+    let mut operations = vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec!["F1".into(), TEXT_SIZE.into()]),
+        Operation::new("TL", vec![leading.into()]),
+        Operation::new("Td", vec![TEXT_MARGIN.into(), top.into()]),
+    ];
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            operations.push(Operation::new("T*", vec![]));
+        }
+        let latin1: Vec<u8> = line
+            .chars()
+            .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+            .collect();
+        operations.push(Operation::new("Tj", vec![Object::string_literal(latin1)]));
+    }
+    operations.push(Operation::new("ET", vec![]));
+
+    // Encoding a handful of text operators cannot fail
+    let content = Content { operations }.encode().unwrap_or_default();
+    let resources = dictionary! {
+        "Font" => dictionary! { "F1" => font_id }
+    };
+    add_page(doc, size, content, resources)
+}
+
+/// Adds a page of `size` drawing `content` with `resources`. Its `/Parent` is set once
+/// the page is placed in the page tree.
+fn add_page(
+    doc: &mut Document,
+    size: PageSize,
+    content: Vec<u8>,
+    resources: Dictionary,
+) -> ObjectId {
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+    doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Contents" => content_id,
+        "MediaBox" => vec![0.into(), 0.into(), size.width.into(), size.height.into()],
+        "Resources" => resources,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decoded content stream of page `page_id`.
+    fn page_content(doc: &Document, page_id: ObjectId) -> Vec<u8> {
+        let page = doc.get_dictionary(page_id).unwrap();
+        let content_id = page.get(b"Contents").unwrap().as_reference().unwrap();
+        doc.get_object(content_id)
+            .unwrap()
+            .as_stream()
+            .unwrap()
+            .content
+            .clone()
+    }
+
+    fn numbers(operands: &[Object]) -> Vec<f32> {
+        operands.iter().map(|n| n.as_float().unwrap()).collect()
+    }
+
+    /// Lays a 400 x 200 image out on an A4 page with half-inch margins. Returns the page
+    /// `/MediaBox`, the operands of the `cm` placing the image and the pixel size of the
+    /// image stored.
+    fn lay_out(fit: FitMode) -> (Vec<f32>, Vec<f32>, (i64, i64)) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("wide.png");
+        image::RgbImage::new(400, 200).save(&path).unwrap();
+        let options = ImagePageOptions {
+            paper: PaperSize::A4,
+            orientation: Orientation::Portrait,
+            margin: 36.0,
+            fit,
+            max_dpi: 72.0,
+        };
+
+        let mut doc = Document::with_version("1.7");
+        let page_id = image_page(&mut doc, &path, &options).unwrap();
+
+        let page = doc.get_dictionary(page_id).unwrap();
+        let media_box = numbers(page.get(b"MediaBox").unwrap().as_array().unwrap());
+        let content = Content::decode(&page_content(&doc, page_id)).unwrap();
+        let cm = content
+            .operations
+            .iter()
+            .find(|operation| operation.operator == "cm")
+            .unwrap();
+        let image_id = page
+            .get(b"Resources")
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .and_then(|xobjects| xobjects.get(b"Im1"))
+            .and_then(Object::as_reference)
+            .unwrap();
+        let image = &doc.get_object(image_id).unwrap().as_stream().unwrap().dict;
+        let pixels = (
+            image.get(b"Width").unwrap().as_i64().unwrap(),
+            image.get(b"Height").unwrap().as_i64().unwrap(),
+        );
+        (media_box, numbers(&cm.operands), pixels)
+    }
+
+    #[test]
+    fn fit_shows_the_whole_image_centered() {
+        let (media_box, cm, pixels) = lay_out(FitMode::Fit);
+        assert_eq!(media_box, [0.0, 0.0, 595.0, 842.0]);
+        // 523 points wide, limited by the width inside the margins
+        assert_eq!(cm, [523.0, 0.0, 0.0, 261.5, 36.0, 290.25]);
+        assert_eq!(pixels, (400, 200));
+    }
+
+    #[test]
+    fn fill_covers_the_margins_box_and_crops_the_sides() {
+        let (media_box, cm, pixels) = lay_out(FitMode::Fill);
+        assert_eq!(media_box, [0.0, 0.0, 595.0, 842.0]);
+        assert_eq!(cm, [523.0, 0.0, 0.0, 770.0, 36.0, 36.0]);
+        // Scaled by 770 / 200, only 523 / 3.85 = 136 columns fit
+        assert_eq!(pixels, (136, 200));
+    }
+
+    #[test]
+    fn stretch_covers_the_margins_box_with_every_pixel() {
+        let (media_box, cm, pixels) = lay_out(FitMode::Stretch);
+        assert_eq!(media_box, [0.0, 0.0, 595.0, 842.0]);
+        assert_eq!(cm, [523.0, 0.0, 0.0, 770.0, 36.0, 36.0]);
+        assert_eq!(pixels, (400, 200));
+    }
+
+    #[test]
+    fn transparent_images_get_a_soft_mask() {
+        let mut rgba = image::RgbaImage::from_pixel(4, 2, image::Rgba([10, 20, 30, 255]));
+        rgba.put_pixel(1, 0, image::Rgba([10, 20, 30, 128]));
+        let mut doc = Document::with_version("1.7");
+        let image = flate_image(&mut doc, DynamicImage::ImageRgba8(rgba)).unwrap();
+
+        let mask_id = image.dict.get(b"SMask").unwrap().as_reference().unwrap();
+        let mask = doc.get_object(mask_id).unwrap().as_stream().unwrap();
+        assert_eq!(
+            mask.dict.get(b"ColorSpace").unwrap(),
+            &Object::from("DeviceGray")
+        );
+        assert_eq!(
+            mask.decompressed_content().unwrap(),
+            [255, 128, 255, 255, 255, 255, 255, 255]
+        );
+        assert_eq!(
+            image.decompressed_content().unwrap(),
+            [10, 20, 30].repeat(8)
+        );
+    }
+
+    #[test]
+    fn opaque_images_get_no_soft_mask() {
+        let rgba = image::RgbaImage::from_pixel(4, 2, image::Rgba([10, 20, 30, 255]));
+        let mut doc = Document::with_version("1.7");
+        let image = flate_image(&mut doc, DynamicImage::ImageRgba8(rgba)).unwrap();
+
+        assert!(!image.dict.has(b"SMask"));
+        assert!(doc.objects.is_empty());
+    }
+
+    #[test]
+    fn text_pages_escape_string_delimiters() {
+        let mut doc = Document::with_version("1.7");
+        let line = r"1) draft \ (see";
+        let page_id = text_page(&mut doc, line, PageSize::A4);
+
+        let content = page_content(&doc, page_id);
+        let text = String::from_utf8_lossy(&content);
+        assert!(
+            text.contains(r"(1\) draft \\ \(see) Tj"),
+            "unexpected content: {}",
+            text
+        );
+        let shown: Vec<Object> = Content::decode(&content)
+            .unwrap()
+            .operations
+            .into_iter()
+            .filter(|operation| operation.operator == "Tj")
+            .flat_map(|operation| operation.operands)
+            .collect();
+        assert_eq!(shown, [Object::string_literal(line)]);
+    }
+}
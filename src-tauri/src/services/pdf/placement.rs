@@ -0,0 +1,243 @@
+use lopdf::content::Content;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::HashMap;
+
+use super::{resolved_page, stream_data};
+
+/// Form XObjects can draw other forms; anything nested deeper than this is a loop.
+const MAX_FORM_DEPTH: usize = 12;
+
+/// A transformation matrix `[a b c d e f]`, as used by the `cm` operator.
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Largest size, in points, each image XObject is drawn at across the pages of `doc`,
+/// found by following the transformation matrix through page contents and the form
+/// XObjects they draw. Images drawn only from elsewhere, such as annotation appearances
+/// or patterns, are missing.
+pub(crate) fn image_placements(doc: &Document) -> HashMap<ObjectId, (f32, f32)> {
+    let mut sizes = HashMap::new();
+    for page_id in doc.get_pages().into_values() {
+        let Ok(page) = resolved_page(doc, page_id) else {
+            continue;
+        };
+        let Ok(content) = doc.get_page_content(page_id) else {
+            continue;
+        };
+        let resources = page
+            .get(b"Resources")
+            .ok()
+            .and_then(|resources| dictionary(doc, resources));
+        if let Some(resources) = resources {
+            walk_content(doc, &content, resources, IDENTITY, 0, &mut sizes);
+        }
+    }
+    sizes
+}
+
+fn walk_content(
+    doc: &Document,
+    content: &[u8],
+    resources: &Dictionary,
+    base: Matrix,
+    depth: usize,
+    sizes: &mut HashMap<ObjectId, (f32, f32)>,
+) {
+    let Ok(content) = Content::decode(content) else {
+        return;
+    };
+    let xobjects = resources
+        .get(b"XObject")
+        .ok()
+        .and_then(|xobjects| dictionary(doc, xobjects));
+
+    let mut ctm = base;
+    let mut saved = Vec::new();
+    for operation in &content.operations {
+        match operation.operator.as_str() {
+            "q" => saved.push(ctm),
+            "Q" => ctm = saved.pop().unwrap_or(base),
+            "cm" => {
+                if let Some(matrix) = matrix(&operation.operands) {
+                    ctm = multiply(matrix, ctm);
+                }
+            }
+            "Do" => {
+                let id = operation
+                    .operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| xobjects?.get(name).ok())
+                    .and_then(|xobject| xobject.as_reference().ok());
+                if let Some(id) = id {
+                    draw_xobject(doc, id, resources, ctm, depth, sizes);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw_xobject(
+    doc: &Document,
+    id: ObjectId,
+    resources: &Dictionary,
+    ctm: Matrix,
+    depth: usize,
+    sizes: &mut HashMap<ObjectId, (f32, f32)>,
+) {
+    let Ok(stream) = doc.get_object(id).and_then(Object::as_stream) else {
+        return;
+    };
+    match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+        // Images fill the unit square, so the matrix gives their size
+        Ok(b"Image") => {
+            let width = ctm[0].hypot(ctm[1]);
+            let height = ctm[2].hypot(ctm[3]);
+            let size = sizes.entry(id).or_insert((0.0, 0.0));
+            size.0 = size.0.max(width);
+            size.1 = size.1.max(height);
+        }
+        Ok(b"Form") if depth < MAX_FORM_DEPTH => {
+            let Some(content) = stream_data(stream) else {
+                return;
+            };
+            let form_matrix = stream
+                .dict
+                .get(b"Matrix")
+                .and_then(Object::as_array)
+                .ok()
+                .and_then(|operands| matrix(operands))
+                .unwrap_or(IDENTITY);
+            // Forms without resources of their own use those of whatever draws them
+            let form_resources = stream
+                .dict
+                .get(b"Resources")
+                .ok()
+                .and_then(|resources| dictionary(doc, resources))
+                .unwrap_or(resources);
+            walk_content(
+                doc,
+                &content,
+                form_resources,
+                multiply(form_matrix, ctm),
+                depth + 1,
+                sizes,
+            );
+        }
+        _ => {}
+    }
+}
+
+fn matrix(operands: &[Object]) -> Option<Matrix> {
+    let values: Vec<f32> = operands
+        .iter()
+        .map(Object::as_float)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    values.try_into().ok()
+}
+
+/// The matrix that applies `m`, then `n`.
+fn multiply(m: Matrix, n: Matrix) -> Matrix {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
+
+fn dictionary<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Dictionary> {
+    doc.dereference(object).ok()?.1.as_dict().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    fn image(doc: &mut Document) -> ObjectId {
+        doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 1,
+                "Height" => 1,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![0],
+        ))
+    }
+
+    /// Gives `doc` a single page drawing `content` with the XObjects `xobjects`.
+    fn set_page(doc: &mut Document, content: &str, xobjects: Dictionary) {
+        let pages_id = doc.new_object_id();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.as_bytes().to_vec()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Contents" => content_id,
+            "Resources" => dictionary! { "XObject" => xobjects },
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => 1,
+                "Kids" => vec![page_id.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+    }
+
+    #[test]
+    fn images_drawn_twice_report_their_largest_size() {
+        let mut doc = Document::with_version("1.7");
+        let image_id = image(&mut doc);
+        set_page(
+            &mut doc,
+            "q 100 0 0 50 0 0 cm /Im1 Do Q q 200 0 0 40 300 300 cm /Im1 Do Q",
+            dictionary! { "Im1" => image_id },
+        );
+
+        let placements = image_placements(&doc);
+        assert_eq!(placements[&image_id], (200.0, 50.0));
+    }
+
+    #[test]
+    fn images_in_forms_are_scaled_by_the_form_matrix() {
+        let mut doc = Document::with_version("1.7");
+        let image_id = image(&mut doc);
+        let form_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+                "Matrix" => vec![2.into(), 0.into(), 0.into(), 3.into(), 0.into(), 0.into()],
+                "Resources" => dictionary! {
+                    "XObject" => dictionary! { "Im2" => image_id },
+                },
+            },
+            b"q 10 0 0 10 0 0 cm /Im2 Do Q".to_vec(),
+        ));
+        set_page(
+            &mut doc,
+            "q 0.5 0 0 0.5 100 100 cm /Fm1 Do Q",
+            dictionary! { "Fm1" => form_id },
+        );
+
+        let placements = image_placements(&doc);
+        assert_eq!(placements[&image_id], (10.0, 15.0));
+        assert!(!placements.contains_key(&form_id));
+    }
+}
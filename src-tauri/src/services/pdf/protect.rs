@@ -1,139 +1,380 @@
-use lopdf::Document;
-use serde::Serialize;
-use std::path::PathBuf;
-use tauri::{AppHandle, Manager};
-use tauri_plugin_dialog::DialogExt;
-use tauri_plugin_shell::ShellExt;
-
-#[derive(Serialize)]
-pub enum PdfResult {
-    Message { message: String },
-    TempPath { path: String },
-}
-
-pub async fn protect_pdf(
-    app: AppHandle,
-    input_path: String,
-    password: String,
-) -> Result<String, String> {
-    if !std::path::Path::new(&input_path).exists() {
-        return Err(format!("Input file not found at: {}", input_path));
+use super::{save_document, write_output, write_output_file};
+use crate::error::{SliceError, SliceResult};
+use crate::services::job::{Job, Step};
+use lopdf::encryption::crypt_filters::{Aes128CryptFilter, Aes256CryptFilter, CryptFilter};
+use lopdf::encryption::{EncryptionState, EncryptionVersion, Permissions};
+use lopdf::{Document, Object, StringFormat};
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+
+/// Cipher used to encrypt the document.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Encryption {
+    /// AES-256, security handler revision 6 (PDF 2.0).
+    #[default]
+    Aes256,
+    /// AES-128, security handler revision 4 (PDF 1.6), for older readers.
+    Aes128,
+}
+
+/// How far printing is allowed.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PrintPermission {
+    None,
+    /// Printing only at a degraded resolution.
+    LowQuality,
+    #[default]
+    HighQuality,
+}
+
+/// What someone who opened the document with the user password may do. Everything is
+/// allowed by default.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PdfPermissions {
+    pub print: PrintPermission,
+    /// Changes other than annotations, form filling and page assembly.
+    pub modify: bool,
+    /// Copying or extracting text and graphics.
+    pub copy: bool,
+    /// Adding or changing annotations, which also allows filling forms.
+    pub annotate: bool,
+    pub fill_forms: bool,
+    /// Inserting, deleting and rotating pages, and creating bookmarks.
+    pub assemble: bool,
+}
+
+impl Default for PdfPermissions {
+    fn default() -> Self {
+        PdfPermissions {
+            print: PrintPermission::HighQuality,
+            modify: true,
+            copy: true,
+            annotate: true,
+            fill_forms: true,
+            assemble: true,
+        }
+    }
+}
+
+impl PdfPermissions {
+    fn to_lopdf(self) -> Permissions {
+        // Extraction for accessibility stays on, PDF 2.0 readers ignore the bit anyway.
+        let mut permissions = Permissions::COPYABLE_FOR_ACCESSIBILITY;
+        match self.print {
+            PrintPermission::None => {}
+            PrintPermission::LowQuality => permissions |= Permissions::PRINTABLE,
+            PrintPermission::HighQuality => {
+                permissions |= Permissions::PRINTABLE | Permissions::PRINTABLE_IN_HIGH_QUALITY
+            }
+        }
+        permissions.set(Permissions::MODIFIABLE, self.modify);
+        permissions.set(Permissions::COPYABLE, self.copy);
+        permissions.set(Permissions::ANNOTABLE, self.annotate);
+        permissions.set(Permissions::FILLABLE, self.fill_forms);
+        permissions.set(Permissions::ASSEMBLABLE, self.assemble);
+        permissions
+    }
+
+    fn qpdf_args(self) -> Vec<String> {
+        let flag =
+            |name: &str, allowed: bool| format!("--{}={}", name, if allowed { "y" } else { "n" });
+        let print = match self.print {
+            PrintPermission::None => "none",
+            PrintPermission::LowQuality => "low",
+            PrintPermission::HighQuality => "full",
+        };
+        vec![
+            format!("--print={}", print),
+            flag("modify-other", self.modify),
+            flag("extract", self.copy),
+            flag("annotate", self.annotate),
+            flag("form", self.fill_forms),
+            flag("assemble", self.assemble),
+        ]
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtectOptions {
+    /// Needed to open the document. Leave empty for a document anyone can open but that is
+    /// still held to `permissions`.
+    #[serde(default)]
+    pub user_password: String,
+    /// Lifts the `permissions` restrictions. Defaults to the user password.
+    #[serde(default)]
+    pub owner_password: Option<String>,
+    #[serde(default)]
+    pub permissions: PdfPermissions,
+    #[serde(default)]
+    pub encryption: Encryption,
+}
+
+impl ProtectOptions {
+    fn owner_password(&self) -> &str {
+        match &self.owner_password {
+            Some(owner) if !owner.is_empty() => owner,
+            _ => &self.user_password,
+        }
+    }
+
+    fn validate(&self) -> SliceResult<()> {
+        if self.owner_password().is_empty() {
+            return Err(SliceError::invalid(
+                "Set a password to open the document or an owner password to restrict it",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Encrypts the PDF at `input_path` and writes it to `output`.
+pub fn protect_pdf<W: Write>(
+    input_path: &Path,
+    output: &mut W,
+    options: &ProtectOptions,
+    job: &Job,
+) -> SliceResult<()> {
+    options.validate()?;
+    job.report(Step::Load, 0, 1, Some(input_path.display().to_string()));
+    job.checkpoint()?;
+    let mut doc = super::load_document(input_path, None)?;
+    ensure_file_id(&mut doc);
+
+    let state = encryption_state(&doc, options)
+        .map_err(|e| SliceError::internal(format!("Failed to encrypt PDF: {}", e)))?;
+    doc.encrypt(&state)
+        .map_err(|e| SliceError::internal(format!("Failed to encrypt PDF: {}", e)))?;
+
+    save_document(&mut doc, output, job)
+}
+
+/// Opens the PDF at `input_path` with `password` and writes an unencrypted copy to `output`.
+/// A document that opens without a password only needs an empty one, its owner
+/// restrictions are dropped along with the encryption.
+pub fn decrypt_pdf<W: Write>(
+    input_path: &Path,
+    output: &mut W,
+    password: &str,
+    job: &Job,
+) -> SliceResult<()> {
+    job.report(Step::Load, 0, 1, Some(input_path.display().to_string()));
+    job.checkpoint()?;
+    let mut doc = Document::load_with_password(input_path, password)
+        .map_err(|e| SliceError::from_lopdf(input_path, e))?;
+
+    if !doc.was_encrypted() {
+        return Err(SliceError::invalid(format!(
+            "{} is not password protected",
+            input_path.display()
+        )));
     }
 
-    let file_path = app
-        .dialog()
-        .file()
-        .set_file_name("protected.pdf")
-        .add_filter("PDF", &["pdf"][..])
-        .blocking_save_file();
+    save_document(&mut doc, output, job)
+}
+
+/// Encrypts `input_path` into `save_path`. When lopdf cannot process the file and a `qpdf`
+/// command is given, the work is handed to qpdf instead, which also writes to a temporary
+/// file that only replaces `save_path` once qpdf succeeded and the job was not cancelled.
+pub fn protect_file(
+    input_path: &Path,
+    save_path: &Path,
+    options: &ProtectOptions,
+    qpdf: Option<Command>,
+    job: &Job,
+) -> SliceResult<()> {
+    let result = write_output(save_path, |output| {
+        protect_pdf(input_path, output, options, job)
+    });
+
+    match (result, qpdf) {
+        (Err(err), Some(qpdf)) if needs_fallback(&err) => {
+            let fallback = write_output_file(save_path, |partial| {
+                qpdf_protect(qpdf, input_path, partial, options)?;
+                job.checkpoint()
+            });
+            keep_native_error(err, fallback)
+        }
+        (result, _) => result,
+    }
+}
+
+/// Writes an unencrypted copy of `input_path` to `save_path`, falling back to `qpdf` like
+/// [`protect_file`].
+pub fn decrypt_file(
+    input_path: &Path,
+    save_path: &Path,
+    password: &str,
+    qpdf: Option<Command>,
+    job: &Job,
+) -> SliceResult<()> {
+    let result = write_output(save_path, |output| {
+        decrypt_pdf(input_path, output, password, job)
+    });
+
+    match (result, qpdf) {
+        (Err(err), Some(qpdf)) if needs_fallback(&err) => {
+            let fallback = write_output_file(save_path, |partial| {
+                qpdf_decrypt(qpdf, input_path, partial, password)?;
+                job.checkpoint()
+            });
+            keep_native_error(err, fallback)
+        }
+        (result, _) => result,
+    }
+}
+
+/// Encrypts `input_path` using the given `qpdf` command and writes it to `save_path`.
+pub fn qpdf_protect(
+    mut qpdf: Command,
+    input_path: &Path,
+    save_path: &Path,
+    options: &ProtectOptions,
+) -> SliceResult<()> {
+    if !input_path.exists() {
+        return Err(SliceError::invalid(format!(
+            "Input file not found at: {}",
+            input_path.display()
+        )));
+    }
 
-    let save_path = match file_path {
-        Some(path) => path.into_path().map_err(|_| "Invalid path")?,
-        _ => return Err("Cancelled".into()),
+    options.validate()?;
+    qpdf.args([
+        "--encrypt",
+        &options.user_password,
+        options.owner_password(),
+    ]);
+    match options.encryption {
+        Encryption::Aes256 => qpdf.arg("256"),
+        Encryption::Aes128 => qpdf.args(["128", "--use-aes=y"]),
     };
+    qpdf.args(options.permissions.qpdf_args());
 
-    let save_path_str = save_path.to_string_lossy().to_string();
-    let sidecar_name = "qpdf";
-    let sidecar = app.shell()
-        .sidecar(sidecar_name)
-        .map_err(|e| {
-            let err = format!("[ERROR] Sidecar 'qpdf' not found in bundle. Check tauri.conf.json and binary names. Error: {}", e);
-            println!("{}", err);
-            err
-        })?;
-
-    let output = sidecar
-        .args([
-            "--encrypt",
-            &password,
-            &password,
-            "256",
-            "--",
-            &input_path,
-            &save_path_str,
-        ])
+    let output = qpdf
+        .arg("--")
+        .arg(input_path)
+        .arg(save_path)
         .output()
-        .await
-        .map_err(|e| format!("[ERROR] Failed to execute qpdf: {}", e))?;
+        .map_err(qpdf_missing)?;
 
-    // 3. Detailed Status Check
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        println!("[DEBUG] QPDF Stderr: {}", stderr);
-        println!("[DEBUG] QPDF Stdout: {}", stdout);
-        return Err(format!(
+        return Err(SliceError::internal(format!(
             "QPDF Error (Code {:?}): {}",
             output.status.code(),
-            stderr
-        ));
+            String::from_utf8_lossy(&output.stderr)
+        )));
     }
 
-    Ok("PDF encrypted successfully".to_string())
-}
-
-pub async fn decrypt_pdf_service(
-    app: AppHandle,
-    input_path: String,
-    password: String,
-    temp: bool,
-) -> Result<PdfResult, String> {
-    let save_path: PathBuf = if temp {
-        let mut path = app.path().temp_dir().map_err(|_| "Temp dir error")?;
-        path.push("slice_pdf_decrypted.pdf");
-        path
-    } else {
-        app.dialog()
-            .file()
-            .set_file_name("slice_pdf_decrypted.pdf")
-            .add_filter("PDF", &["pdf"][..])
-            .blocking_save_file()
-            .ok_or("Save cancelled")?
-            .into_path()
-            .map_err(|_| "Invalid path")?
-    };
+    Ok(())
+}
 
-    let save_path_str = save_path.to_string_lossy().to_string();
-    let sidecar_name = "qpdf";
-    // Use Sidecar for Decryption
-    let output = app
-        .shell()
-        .sidecar(sidecar_name)
-        .map_err(|e| e.to_string())?
-        .args([
-            format!("--password={}", password),
-            "--decrypt".to_string(),
-            input_path,
-            save_path_str.clone(),
-        ])
+/// Removes the encryption from `input_path` using the given `qpdf` command and writes the
+/// unlocked copy to `save_path`.
+pub fn qpdf_decrypt(
+    mut qpdf: Command,
+    input_path: &Path,
+    save_path: &Path,
+    password: &str,
+) -> SliceResult<()> {
+    let output = qpdf
+        .arg(format!("--password={}", password))
+        .arg("--decrypt")
+        .arg(input_path)
+        .arg(save_path)
         .output()
-        .await
-        .map_err(|e| e.to_string())?;
+        .map_err(qpdf_missing)?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
         if stderr.contains("invalid password") {
-            return Err("Invalid password".into());
+            return Err(SliceError::WrongPassword {
+                path: input_path.display().to_string(),
+            });
         }
-        return Err(stderr.to_string());
+        return Err(SliceError::internal(stderr));
     }
 
-    // 🔥 VERIFY using lopdf (Optional but safe)
-    let decrypted =
-        Document::load(&save_path_str).map_err(|_| "Failed to read decrypted output")?;
+    Ok(())
+}
+
+fn encryption_state(doc: &Document, options: &ProtectOptions) -> lopdf::Result<EncryptionState> {
+    let user_password = options.user_password.as_str();
+    let owner_password = options.owner_password();
+    let permissions = options.permissions.to_lopdf();
+
+    // R6 uses a random file key instead of one derived from the document.
+    let mut file_encryption_key = [0u8; 32];
+    rand::rng().fill(&mut file_encryption_key);
+
+    let version = match options.encryption {
+        Encryption::Aes256 => {
+            let filter: Arc<dyn CryptFilter> = Arc::new(Aes256CryptFilter);
+            EncryptionVersion::V5 {
+                encrypt_metadata: true,
+                crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), filter)]),
+                file_encryption_key: &file_encryption_key,
+                stream_filter: b"StdCF".to_vec(),
+                string_filter: b"StdCF".to_vec(),
+                owner_password,
+                user_password,
+                permissions,
+            }
+        }
+        Encryption::Aes128 => {
+            let filter: Arc<dyn CryptFilter> = Arc::new(Aes128CryptFilter);
+            EncryptionVersion::V4 {
+                document: doc,
+                encrypt_metadata: true,
+                crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), filter)]),
+                stream_filter: b"StdCF".to_vec(),
+                string_filter: b"StdCF".to_vec(),
+                owner_password,
+                user_password,
+                permissions,
+            }
+        }
+    };
 
-    if decrypted.is_encrypted() {
-        let _ = std::fs::remove_file(&save_path_str);
-        return Err("File is still encrypted".into());
+    EncryptionState::try_from(version)
+}
+
+/// The AES-128 key is derived from the trailer `/ID`, which documents built by lopdf lack.
+fn ensure_file_id(doc: &mut Document) {
+    if doc.trailer.get(b"ID").is_ok() {
+        return;
     }
+    let mut id = [0u8; 16];
+    rand::rng().fill(&mut id);
+    let id = Object::String(id.to_vec(), StringFormat::Hexadecimal);
+    doc.trailer.set("ID", vec![id.clone(), id]);
+}
+
+/// Errors that mean lopdf could not handle the file, as opposed to a bad request.
+fn needs_fallback(err: &SliceError) -> bool {
+    matches!(
+        err,
+        SliceError::ParseFailure { .. } | SliceError::Internal { .. }
+    )
+}
+
+/// A missing qpdf should not hide why the native path failed.
+fn keep_native_error(native: SliceError, fallback: SliceResult<()>) -> SliceResult<()> {
+    match fallback {
+        Err(SliceError::SidecarMissing { .. }) => Err(native),
+        other => other,
+    }
+}
 
-    if temp {
-        Ok(PdfResult::TempPath {
-            path: save_path_str,
-        })
-    } else {
-        Ok(PdfResult::Message {
-            message: "PDF decrypted successfully".into(),
-        })
+fn qpdf_missing(err: std::io::Error) -> SliceError {
+    SliceError::SidecarMissing {
+        name: "qpdf".to_string(),
+        message: err.to_string(),
     }
 }
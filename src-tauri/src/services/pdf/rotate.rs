@@ -1,5 +1,9 @@
-use lopdf::{Document, Object};
-use tauri_plugin_dialog::DialogExt;
+use super::{load_document, page_label, save_document};
+use crate::error::{SliceError, SliceResult};
+use crate::services::job::{Job, Step};
+use lopdf::Object;
+use std::io::Write;
+use std::path::Path;
 
 #[derive(serde::Deserialize)]
 pub struct RotatePageInstructions {
@@ -8,57 +12,60 @@ pub struct RotatePageInstructions {
     pub filepath: String,
 }
 
-pub async fn rotate_pdf(
-    app: tauri::AppHandle,
-    instructions: Vec<RotatePageInstructions>,
-) -> Result<(), String> {
-    if instructions.is_empty() {
-        return Err("No instructions provided".into());
-    }
-
-    let file_path = app
-        .dialog()
-        .file()
-        .set_file_name("rotated_document_by_slice_PDF.pdf")
-        .add_filter("PDF", &["pdf"][..])
-        .blocking_save_file();
-
-    let save_path = match file_path {
-        Some(path) => path.into_path().map_err(|_| "Invalid path")?,
-        _none => return Err("Cancelled".into()),
-    };
+#[derive(serde::Serialize, Debug)]
+pub struct RotateReport {
+    pub pages: usize,
+}
 
-    tauri::async_runtime::spawn_blocking(move || {
-        let primary_path = &instructions[0].filepath;
-        let mut doc = Document::load(primary_path).map_err(|e| e.to_string())?;
+/// Applies `instructions` to the PDF named by the first instruction and writes it to `output`.
+/// `password` opens that PDF when it is encrypted.
+pub fn rotate_pdf<W: Write>(
+    instructions: &[RotatePageInstructions],
+    password: Option<&str>,
+    output: &mut W,
+    job: &Job,
+) -> SliceResult<RotateReport> {
+    let primary_path = &instructions
+        .first()
+        .ok_or_else(|| SliceError::invalid("No instructions provided"))?
+        .filepath;
+    let mut doc = load_document(Path::new(primary_path), password)?;
+    let mut pages_rotated = 0;
 
-        // 2. Apply rotations
-        for inst in instructions {
-            let pages = doc.get_pages();
+    // 2. Apply rotations
+    for (index, inst) in instructions.iter().enumerate() {
+        job.report(
+            Step::Page,
+            index,
+            instructions.len(),
+            Some(page_label(&inst.filepath, inst.pagenumber)),
+        );
+        job.checkpoint()?;
+        let pages = doc.get_pages();
 
-            if let Some(&page_id) = pages.get(&inst.pagenumber) {
-                let page_dict = doc
-                    .get_object_mut(page_id)
-                    .and_then(Object::as_dict_mut)
-                    .map_err(|e| e.to_string())?;
+        if let Some(&page_id) = pages.get(&inst.pagenumber) {
+            let page_dict = doc
+                .get_object_mut(page_id)
+                .and_then(Object::as_dict_mut)
+                .map_err(|e| SliceError::parse(Path::new(primary_path), e))?;
 
-                let current_rotation = page_dict
-                    .get(b"Rotate")
-                    .and_then(|obj| obj.as_i64())
-                    .unwrap_or(0);
+            let current_rotation = page_dict
+                .get(b"Rotate")
+                .and_then(|obj| obj.as_i64())
+                .unwrap_or(0);
 
-                // 2. Calculate new rotation
-                let new_rotation = (current_rotation + inst.rotation as i64) % 360;
+            // 2. Calculate new rotation
+            let new_rotation = (current_rotation + inst.rotation as i64) % 360;
 
-                // 3. Update the dictionary
-                page_dict.set("Rotate", Object::Integer(new_rotation));
-            }
+            // 3. Update the dictionary
+            page_dict.set("Rotate", Object::Integer(new_rotation));
+            pages_rotated += 1;
         }
+    }
 
-        // 3. Save the modified document
-        doc.save(save_path).map_err(|e| e.to_string())?;
-        Ok::<(), String>(())
+    // 3. Save the modified document
+    save_document(&mut doc, output, job)?;
+    Ok(RotateReport {
+        pages: pages_rotated,
     })
-    .await
-    .map_err(|e| e.to_string())?
 }
@@ -0,0 +1,864 @@
+use super::outline::top_level_outline;
+use super::{load_document, reachable_objects, retain_reachable, save_document, write_output};
+use crate::error::{SliceError, SliceResult};
+use crate::services::job::{Job, Step};
+use lopdf::{Document, Object, ObjectId};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Name given to each part when the caller does not pick one.
+pub const DEFAULT_NAME_TEMPLATE: &str = "{name}-{part}";
+
+/// How to cut a document into parts.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(tag = "mode", rename_all = "camelCase")]
+pub enum SplitMode {
+    /// One part per range of an expression such as `1-3,5,8-`.
+    Ranges { ranges: String },
+    /// Consecutive parts of `pages` pages, the last one may be shorter.
+    Every { pages: u32 },
+    /// One part per page.
+    Burst,
+    /// One part per top-level bookmark. Pages before the first bookmark get a part of
+    /// their own.
+    Bookmarks,
+    /// Parts of at most `megabytes` MiB each. A page that is larger on its own still
+    /// becomes a single, oversized part.
+    MaxSize { megabytes: f64 },
+}
+
+/// Inclusive, 1-based range of pages that ends up in one part.
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageSpan {
+    pub first: u32,
+    pub last: u32,
+}
+
+impl PageSpan {
+    fn contains(&self, page: u32) -> bool {
+        (self.first..=self.last).contains(&page)
+    }
+}
+
+/// Pages of one part and, when cut at a bookmark, the bookmark's title.
+#[derive(Clone, Debug)]
+pub struct PartPlan {
+    pub pages: PageSpan,
+    pub title: Option<String>,
+}
+
+impl From<PageSpan> for PartPlan {
+    fn from(pages: PageSpan) -> Self {
+        PartPlan { pages, title: None }
+    }
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct SplitPart {
+    pub path: String,
+    #[serde(flatten)]
+    pub pages: PageSpan,
+    pub title: Option<String>,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct SplitReport {
+    pub parts: Vec<SplitPart>,
+}
+
+/// Parses a range expression like `1-3,5,8-` against a document of `page_count` pages.
+/// `8-` runs to the last page and `-3` starts at the first. Pages past the end are left
+/// for the caller to report, an open range that starts there covers just its first page.
+pub fn parse_ranges(expression: &str, page_count: u32) -> SliceResult<Vec<PageSpan>> {
+    let mut spans = Vec::new();
+
+    for item in expression.split(',').map(str::trim) {
+        if item.is_empty() {
+            continue;
+        }
+        let invalid = || SliceError::invalid(format!("Invalid page range '{}'", item));
+        let page = |value: &str| value.trim().parse::<u32>().map_err(|_| invalid());
+
+        let span = match item.split_once('-') {
+            None => {
+                let page = page(item)?;
+                PageSpan {
+                    first: page,
+                    last: page,
+                }
+            }
+            Some((first, last)) => {
+                let first = if first.trim().is_empty() {
+                    1
+                } else {
+                    page(first)?
+                };
+                let last = if last.trim().is_empty() {
+                    page_count.max(first)
+                } else {
+                    page(last)?
+                };
+                PageSpan { first, last }
+            }
+        };
+
+        if span.first == 0 || span.first > span.last {
+            return Err(invalid());
+        }
+        spans.push(span);
+    }
+
+    if spans.is_empty() {
+        return Err(SliceError::invalid("No page ranges given"));
+    }
+    Ok(spans)
+}
+
+/// Works out the parts `mode` cuts `doc` into. `pages` is `doc.get_pages()`.
+pub fn plan_parts(
+    doc: &Document,
+    pages: &BTreeMap<u32, ObjectId>,
+    mode: &SplitMode,
+    job: &Job,
+) -> SliceResult<Vec<PartPlan>> {
+    let page_count = pages.len() as u32;
+    let spans = match mode {
+        SplitMode::Ranges { ranges } => parse_ranges(ranges, page_count)?,
+        SplitMode::Every { pages: 0 } => {
+            return Err(SliceError::invalid("Pages per part must be at least 1"))
+        }
+        SplitMode::Every { pages } => (1..=page_count)
+            .step_by(*pages as usize)
+            .map(|first| PageSpan {
+                first,
+                last: (first + pages - 1).min(page_count),
+            })
+            .collect(),
+        SplitMode::Burst => (1..=page_count)
+            .map(|page| PageSpan {
+                first: page,
+                last: page,
+            })
+            .collect(),
+        SplitMode::Bookmarks => return bookmark_parts(doc, pages),
+        SplitMode::MaxSize { megabytes } => return size_parts(doc, pages, *megabytes, job),
+    };
+    Ok(spans.into_iter().map(PartPlan::from).collect())
+}
+
+/// Cuts at the first page of every top-level bookmark.
+fn bookmark_parts(doc: &Document, pages: &BTreeMap<u32, ObjectId>) -> SliceResult<Vec<PartPlan>> {
+    let numbers: HashMap<ObjectId, u32> = pages.iter().map(|(&number, &id)| (id, number)).collect();
+
+    // First bookmark wins when several start on the same page.
+    let mut starts: BTreeMap<u32, String> = BTreeMap::new();
+    for item in top_level_outline(doc) {
+        if let Some(&number) = item.page.and_then(|page| numbers.get(&page)) {
+            starts.entry(number).or_insert(item.title);
+        }
+    }
+    if starts.is_empty() {
+        return Err(SliceError::invalid("This PDF has no bookmarks to split at"));
+    }
+
+    let page_count = pages.len() as u32;
+    let mut parts = Vec::with_capacity(starts.len() + 1);
+    let first_start = *starts.keys().next().unwrap_or(&1);
+    if first_start > 1 {
+        parts.push(PartPlan::from(PageSpan {
+            first: 1,
+            last: first_start - 1,
+        }));
+    }
+    let mut starts = starts.into_iter().peekable();
+    while let Some((first, title)) = starts.next() {
+        let last = starts.peek().map_or(page_count, |(next, _)| next - 1);
+        parts.push(PartPlan {
+            pages: PageSpan { first, last },
+            title: Some(title),
+        });
+    }
+    Ok(parts)
+}
+
+/// Greedily grows each part while it still fits in `megabytes`. Candidate parts are
+/// actually serialized to measure them, probing with a doubling step and then bisecting,
+/// so a part of `n` pages costs about `2 log n` trial saves.
+fn size_parts(
+    doc: &Document,
+    pages: &BTreeMap<u32, ObjectId>,
+    megabytes: f64,
+    job: &Job,
+) -> SliceResult<Vec<PartPlan>> {
+    if megabytes.is_nan() || megabytes <= 0.0 {
+        return Err(SliceError::invalid("Maximum part size must be above 0 MB"));
+    }
+    let budget = (megabytes * 1024.0 * 1024.0) as u64;
+    let page_count = pages.len() as u32;
+    let fits = |first: u32, last: u32| -> SliceResult<bool> {
+        job.checkpoint()?;
+        Ok(part_size(doc, pages, PageSpan { first, last })? <= budget)
+    };
+
+    let mut parts = Vec::new();
+    let mut first = 1;
+    while first <= page_count {
+        job.report(Step::Page, first as usize - 1, page_count as usize, None);
+
+        let mut last = first;
+        if fits(first, first)? {
+            // Double the step until a candidate is too big, then bisect the gap.
+            let mut step = 1;
+            let mut too_big = None;
+            while last < page_count {
+                let probe = (last + step).min(page_count);
+                if fits(first, probe)? {
+                    last = probe;
+                    step *= 2;
+                } else {
+                    too_big = Some(probe);
+                    break;
+                }
+            }
+            if let Some(mut high) = too_big {
+                while high - last > 1 {
+                    let middle = last + (high - last) / 2;
+                    if fits(first, middle)? {
+                        last = middle;
+                    } else {
+                        high = middle;
+                    }
+                }
+            }
+        }
+
+        parts.push(PartPlan::from(PageSpan { first, last }));
+        first = last + 1;
+    }
+    Ok(parts)
+}
+
+/// Size in bytes of the file holding the pages in `span`.
+fn part_size(doc: &Document, pages: &BTreeMap<u32, ObjectId>, span: PageSpan) -> SliceResult<u64> {
+    let mut part = extract_pages(doc, pages, span)?;
+    let mut counter = ByteCounter(0);
+    save_document(&mut part, &mut counter, &Job::silent())?;
+    Ok(counter.0)
+}
+
+/// Writer that only counts what goes through it.
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// File name for part `index` (1-based) of `total` parts of the document named `stem`.
+///
+/// `template` may use `{name}`, `{part}`, `{first}`, `{last}` and `{title}`. `{part}` is
+/// zero-padded so the files sort in order, `{title}` falls back to `{name}` for parts
+/// without a bookmark, and `.pdf` is appended when missing.
+pub fn part_file_name(
+    template: &str,
+    stem: &str,
+    index: usize,
+    total: usize,
+    part: &PartPlan,
+) -> SliceResult<String> {
+    let width = total.to_string().len();
+    let title = part
+        .title
+        .as_deref()
+        .map(file_name_safe)
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| stem.to_string());
+    let mut name = template
+        .replace("{name}", stem)
+        .replace("{part}", &format!("{:0width$}", index, width = width))
+        .replace("{first}", &part.pages.first.to_string())
+        .replace("{last}", &part.pages.last.to_string())
+        .replace("{title}", &title);
+
+    if name.trim().is_empty() || name.contains(['/', '\\']) {
+        return Err(SliceError::invalid(format!(
+            "Invalid file name template '{}'",
+            template
+        )));
+    }
+    if !name.to_lowercase().ends_with(".pdf") {
+        name.push_str(".pdf");
+    }
+    Ok(name)
+}
+
+/// `text` with characters that are not allowed in file names replaced, capped at 100
+/// characters.
+fn file_name_safe(text: &str) -> String {
+    let cleaned: String = text
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(100)
+        .collect();
+    cleaned.trim().trim_end_matches('.').to_string()
+}
+
+/// Splits the PDF at `input_path` into `output_dir` according to `mode`, naming the parts
+/// from `name_template`. When anything fails, the parts written so far are removed.
+pub fn split_pdf(
+    input_path: &Path,
+    password: Option<&str>,
+    mode: &SplitMode,
+    output_dir: &Path,
+    name_template: &str,
+    job: &Job,
+) -> SliceResult<SplitReport> {
+    let doc = load_document(input_path, password)?;
+    let pages = doc.get_pages();
+    let parts = plan_parts(&doc, &pages, mode, job)?;
+
+    write_parts(
+        input_path,
+        &doc,
+        &pages,
+        &parts,
+        output_dir,
+        name_template,
+        job,
+    )
+}
+
+/// Writes one file per planned part of `doc` into `output_dir`.
+pub(crate) fn write_parts(
+    input_path: &Path,
+    doc: &Document,
+    pages: &BTreeMap<u32, ObjectId>,
+    parts: &[PartPlan],
+    output_dir: &Path,
+    name_template: &str,
+    job: &Job,
+) -> SliceResult<SplitReport> {
+    let stem = input_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "document".to_string());
+
+    let mut targets = Vec::with_capacity(parts.len());
+    let mut names = HashSet::new();
+    for (index, part) in parts.iter().enumerate() {
+        if part.pages.last as usize > pages.len() {
+            return Err(SliceError::PageOutOfRange {
+                path: input_path.display().to_string(),
+                page: part.pages.last,
+                page_count: pages.len(),
+            });
+        }
+        let name = part_file_name(name_template, &stem, index + 1, parts.len(), part)?;
+        if !names.insert(name.clone()) {
+            return Err(SliceError::invalid(format!(
+                "The name template gives several parts the name '{}', add {{part}} to it",
+                name
+            )));
+        }
+        targets.push((output_dir.join(name), part));
+    }
+
+    let mut written: Vec<SplitPart> = Vec::new();
+    let result = targets
+        .into_iter()
+        .enumerate()
+        .try_for_each(|(index, (path, plan))| {
+            job.report(
+                Step::Part,
+                index,
+                parts.len(),
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string()),
+            );
+            job.checkpoint()?;
+
+            let mut part = extract_pages(doc, pages, plan.pages)?;
+            write_output(&path, |output| {
+                save_document(&mut part, output, &Job::silent())
+            })?;
+            written.push(SplitPart {
+                path: path.display().to_string(),
+                pages: plan.pages,
+                title: plan.title.clone(),
+            });
+            Ok(())
+        });
+
+    if let Err(err) = result {
+        for part in &written {
+            let _ = std::fs::remove_file(PathBuf::from(&part.path));
+        }
+        return Err(err);
+    }
+
+    job.report(Step::Part, parts.len(), parts.len(), None);
+    Ok(SplitReport { parts: written })
+}
+
+/// Copy of `doc` holding only the pages in `span`.
+///
+/// The page tree keeps its shape so inherited attributes still apply. Dropped pages are
+/// left out entirely, so links and bookmarks that pointed at them no longer keep their
+/// content alive, and the outline is removed since most of it would lead nowhere.
+pub(crate) fn extract_pages(
+    doc: &Document,
+    pages: &BTreeMap<u32, ObjectId>,
+    span: PageSpan,
+) -> SliceResult<Document> {
+    let dropped: HashSet<ObjectId> = pages
+        .iter()
+        .filter(|(&number, _)| !span.contains(number))
+        .map(|(_, &id)| id)
+        .collect();
+    let kept = reachable_objects(doc, doc.trailer.iter().map(|(_, value)| value), &dropped);
+
+    let mut part = Document::with_version(doc.version.clone());
+    part.trailer = doc.trailer.clone();
+    part.max_id = doc.max_id;
+    part.objects = doc
+        .objects
+        .iter()
+        .filter(|(id, _)| kept.contains(id))
+        .map(|(id, object)| (*id, object.clone()))
+        .collect();
+
+    let catalog = part
+        .catalog_mut()
+        .map_err(|e| SliceError::internal(format!("Invalid document catalog: {}", e)))?;
+    catalog.remove(b"Outlines");
+    let root = catalog
+        .get(b"Pages")
+        .and_then(Object::as_reference)
+        .map_err(|e| SliceError::internal(format!("Invalid page tree: {}", e)))?;
+
+    prune_page_tree(&mut part, root, &mut HashSet::new());
+    retain_reachable(&mut part);
+    part.compress();
+    Ok(part)
+}
+
+/// Removes kids of the `/Pages` node `node_id` that no longer exist, along with
+/// intermediate nodes left empty, and fixes up `/Count`. Returns the new page count.
+fn prune_page_tree(doc: &mut Document, node_id: ObjectId, visited: &mut HashSet<ObjectId>) -> i64 {
+    if !visited.insert(node_id) {
+        return 0;
+    }
+    let kids: Vec<ObjectId> = match doc
+        .get_dictionary(node_id)
+        .and_then(|node| node.get(b"Kids"))
+        .and_then(Object::as_array)
+    {
+        Ok(kids) => kids
+            .iter()
+            .filter_map(|kid| kid.as_reference().ok())
+            .collect(),
+        Err(_) => return 0,
+    };
+
+    let mut retained = Vec::new();
+    let mut count = 0;
+    for kid in kids {
+        let Ok(dict) = doc.get_dictionary(kid) else {
+            continue;
+        };
+        if dict.has_type(b"Pages") {
+            let pages = prune_page_tree(doc, kid, visited);
+            if pages > 0 {
+                retained.push(Object::Reference(kid));
+                count += pages;
+            }
+        } else {
+            retained.push(Object::Reference(kid));
+            count += 1;
+        }
+    }
+
+    if let Ok(node) = doc.get_dictionary_mut(node_id) {
+        node.set("Kids", retained);
+        node.set("Count", count);
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    fn span(first: u32, last: u32) -> PageSpan {
+        PageSpan { first, last }
+    }
+
+    fn spans(parts: &[PartPlan]) -> Vec<PageSpan> {
+        parts.iter().map(|part| part.pages).collect()
+    }
+
+    /// A document of `count` pages, split over two intermediate `/Pages` nodes so that
+    /// pruning has something to do.
+    fn document(count: u32) -> Document {
+        let mut doc = Document::with_version("1.5");
+        let root_id = doc.new_object_id();
+        let halves = [(1, count / 2), (count / 2 + 1, count)];
+        let mut nodes = Vec::new();
+        for (first, last) in halves {
+            let node_id = doc.new_object_id();
+            let kids: Vec<Object> = (first..=last)
+                .map(|_| {
+                    doc.add_object(dictionary! {
+                        "Type" => "Page",
+                        "Parent" => node_id,
+                        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+                    })
+                    .into()
+                })
+                .collect();
+            doc.objects.insert(
+                node_id,
+                Object::Dictionary(dictionary! {
+                    "Type" => "Pages",
+                    "Parent" => root_id,
+                    "Count" => kids.len() as i64,
+                    "Kids" => kids,
+                }),
+            );
+            nodes.push(Object::Reference(node_id));
+        }
+        doc.objects.insert(
+            root_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => count as i64,
+                "Kids" => nodes,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => root_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    fn plan(count: u32, mode: SplitMode) -> SliceResult<Vec<PartPlan>> {
+        let doc = document(count);
+        plan_parts(&doc, &doc.get_pages(), &mode, &Job::silent())
+    }
+
+    #[test]
+    fn parses_single_pages_and_ranges() {
+        assert_eq!(
+            parse_ranges("1-3,5,8-", 10).unwrap(),
+            [span(1, 3), span(5, 5), span(8, 10)]
+        );
+        assert_eq!(
+            parse_ranges(" -2 , 4 ,, 6 - 7", 10).unwrap(),
+            [span(1, 2), span(4, 4), span(6, 7)]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_ranges() {
+        for expression in ["0", "0-2", "5-3", "a", "1-b", "", " , "] {
+            assert!(
+                matches!(
+                    parse_ranges(expression, 10),
+                    Err(SliceError::InvalidInput { .. })
+                ),
+                "{:?} should be rejected",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn open_ranges_past_the_end_are_out_of_range() {
+        assert_eq!(parse_ranges("8-", 5).unwrap(), [span(8, 8)]);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("doc.pdf");
+        document(5).save(&input).unwrap();
+        for ranges in ["8-", "9", "4-9"] {
+            let mode = SplitMode::Ranges {
+                ranges: ranges.to_string(),
+            };
+            let result = split_pdf(
+                &input,
+                None,
+                &mode,
+                dir.path(),
+                DEFAULT_NAME_TEMPLATE,
+                &Job::silent(),
+            );
+            assert!(
+                matches!(
+                    result,
+                    Err(SliceError::PageOutOfRange { page_count: 5, .. })
+                ),
+                "{:?} should be out of range",
+                ranges
+            );
+        }
+        // Nothing but the input was written
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn every_leaves_the_remainder_to_the_last_part() {
+        let parts = plan(7, SplitMode::Every { pages: 3 }).unwrap();
+        assert_eq!(spans(&parts), [span(1, 3), span(4, 6), span(7, 7)]);
+
+        let parts = plan(4, SplitMode::Every { pages: 5 }).unwrap();
+        assert_eq!(spans(&parts), [span(1, 4)]);
+
+        assert!(matches!(
+            plan(4, SplitMode::Every { pages: 0 }),
+            Err(SliceError::InvalidInput { .. })
+        ));
+    }
+
+    #[test]
+    fn burst_gives_one_part_per_page() {
+        let parts = plan(3, SplitMode::Burst).unwrap();
+        assert_eq!(spans(&parts), [span(1, 1), span(2, 2), span(3, 3)]);
+    }
+
+    #[test]
+    fn part_names_are_padded_and_end_in_pdf() {
+        let part = PartPlan::from(span(4, 6));
+        assert_eq!(
+            part_file_name("{name}-{part}", "doc", 2, 12, &part).unwrap(),
+            "doc-02.pdf"
+        );
+        assert_eq!(
+            part_file_name("{title} {first}-{last}.PDF", "doc", 1, 3, &part).unwrap(),
+            "doc 4-6.PDF"
+        );
+
+        let titled = PartPlan {
+            pages: span(1, 2),
+            title: Some("Part: one?".to_string()),
+        };
+        assert_eq!(
+            part_file_name("{title}", "doc", 1, 3, &titled).unwrap(),
+            "Part_ one_.pdf"
+        );
+        assert!(part_file_name("{name}/{part}", "doc", 1, 3, &part).is_err());
+    }
+
+    #[test]
+    fn templates_that_repeat_a_name_are_rejected() {
+        let doc = document(4);
+        let pages = doc.get_pages();
+        let parts = plan(4, SplitMode::Burst).unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+
+        // `{n}` is not a placeholder, so it does not tell the parts apart either
+        for template in ["{name}", "{name}-{n}"] {
+            let result = write_parts(
+                Path::new("doc.pdf"),
+                &doc,
+                &pages,
+                &parts,
+                dir.path(),
+                template,
+                &Job::silent(),
+            );
+            assert!(
+                matches!(result, Err(SliceError::InvalidInput { .. })),
+                "{:?} should be rejected",
+                template
+            );
+        }
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let report = write_parts(
+            Path::new("doc.pdf"),
+            &doc,
+            &pages,
+            &parts,
+            dir.path(),
+            "{name}-{part}",
+            &Job::silent(),
+        )
+        .unwrap();
+        assert_eq!(report.parts.len(), 4);
+    }
+
+    #[test]
+    fn extracting_pages_prunes_the_page_tree() {
+        let doc = document(6);
+        let pages = doc.get_pages();
+
+        // Pages 1-3 sit under the first node, which is dropped entirely
+        let part = extract_pages(&doc, &pages, span(4, 5)).unwrap();
+        let kept: Vec<ObjectId> = part.get_pages().into_values().collect();
+        assert_eq!(kept, [pages[&4], pages[&5]]);
+
+        let root = part
+            .catalog()
+            .and_then(|catalog| catalog.get(b"Pages"))
+            .and_then(Object::as_reference)
+            .and_then(|root| part.get_dictionary(root))
+            .unwrap();
+        assert_eq!(root.get(b"Count").and_then(Object::as_i64).unwrap(), 2);
+        assert_eq!(
+            root.get(b"Kids").and_then(Object::as_array).unwrap().len(),
+            1
+        );
+        assert!(!part.objects.contains_key(&pages[&1]));
+        assert!(!part.objects.contains_key(&pages[&6]));
+    }
+
+    /// Gives page `page_id` a content stream of `bytes` bytes that do not compress.
+    fn fill_page(doc: &mut Document, page_id: ObjectId, bytes: usize) {
+        let mut state = 0x2545_f491_u32 ^ page_id.0;
+        let noise: Vec<u8> = (0..bytes)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let content_id = doc.add_object(lopdf::Stream::new(dictionary! {}, noise));
+        doc.get_dictionary_mut(page_id)
+            .unwrap()
+            .set("Contents", content_id);
+    }
+
+    /// A top-level bookmark per entry of `items`, each jumping to the given page.
+    fn add_outline(doc: &mut Document, items: &[(&str, ObjectId)]) {
+        let root_id = doc.new_object_id();
+        let ids: Vec<ObjectId> = items.iter().map(|_| doc.new_object_id()).collect();
+        for (index, (title, page_id)) in items.iter().enumerate() {
+            let mut item = dictionary! {
+                "Title" => lopdf::text_string(title),
+                "Parent" => root_id,
+                "Dest" => vec![(*page_id).into(), "Fit".into()],
+            };
+            if index > 0 {
+                item.set("Prev", ids[index - 1]);
+            }
+            if let Some(&next) = ids.get(index + 1) {
+                item.set("Next", next);
+            }
+            doc.objects.insert(ids[index], Object::Dictionary(item));
+        }
+        doc.objects.insert(
+            root_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Outlines",
+                "First" => ids[0],
+                "Last" => ids[ids.len() - 1],
+                "Count" => ids.len() as i64,
+            }),
+        );
+        let catalog_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        doc.get_dictionary_mut(catalog_id)
+            .unwrap()
+            .set("Outlines", root_id);
+    }
+
+    #[test]
+    fn bookmarks_start_the_parts_they_point_at() {
+        let mut doc = document(6);
+        let pages = doc.get_pages();
+        // The second bookmark on page 4 does not cut again
+        add_outline(
+            &mut doc,
+            &[
+                ("Intro", pages[&2]),
+                ("Chapter", pages[&4]),
+                ("Also chapter", pages[&4]),
+            ],
+        );
+
+        let parts = plan_parts(&doc, &pages, &SplitMode::Bookmarks, &Job::silent()).unwrap();
+
+        assert_eq!(spans(&parts), [span(1, 1), span(2, 3), span(4, 6)]);
+        let titles: Vec<Option<&str>> = parts.iter().map(|part| part.title.as_deref()).collect();
+        assert_eq!(titles, [None, Some("Intro"), Some("Chapter")]);
+
+        assert!(matches!(
+            plan(3, SplitMode::Bookmarks),
+            Err(SliceError::InvalidInput { .. })
+        ));
+    }
+
+    #[test]
+    fn size_parts_stay_under_the_limit_unless_a_page_is_larger() {
+        const KIB: usize = 1024;
+        let mut doc = document(6);
+        let pages = doc.get_pages();
+        for (page, size) in [300, 300, 300, 1500, 200, 200].into_iter().enumerate() {
+            fill_page(&mut doc, pages[&(page as u32 + 1)], size * KIB);
+        }
+        let budget = 1024 * KIB as u64;
+
+        let mode = SplitMode::MaxSize { megabytes: 1.0 };
+        let parts = plan_parts(&doc, &pages, &mode, &Job::silent()).unwrap();
+
+        assert_eq!(spans(&parts), [span(1, 3), span(4, 4), span(5, 6)]);
+        for part in &parts {
+            let size = part_size(&doc, &pages, part.pages).unwrap();
+            let single_page = part.pages.first == part.pages.last;
+            assert!(
+                size <= budget || single_page,
+                "{:?} is {} bytes",
+                part,
+                size
+            );
+        }
+        assert!(part_size(&doc, &pages, span(4, 4)).unwrap() > budget);
+    }
+
+    #[test]
+    fn pages_over_the_size_limit_are_still_written() {
+        let mut doc = document(2);
+        let pages = doc.get_pages();
+        fill_page(&mut doc, pages[&1], 64 * 1024);
+        fill_page(&mut doc, pages[&2], 16 * 1024);
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("doc.pdf");
+        doc.save(&input).unwrap();
+        let output_dir = dir.path().join("parts");
+        std::fs::create_dir(&output_dir).unwrap();
+
+        // 32 KiB, which the first page alone is over
+        let mode = SplitMode::MaxSize {
+            megabytes: 1.0 / 32.0,
+        };
+        let report = split_pdf(
+            &input,
+            None,
+            &mode,
+            &output_dir,
+            DEFAULT_NAME_TEMPLATE,
+            &Job::silent(),
+        )
+        .unwrap();
+
+        let written: Vec<PageSpan> = report.parts.iter().map(|part| part.pages).collect();
+        assert_eq!(written, [span(1, 1), span(2, 2)]);
+        for part in &report.parts {
+            let saved = Document::load(&part.path).unwrap();
+            assert_eq!(saved.get_pages().len(), 1);
+        }
+    }
+}
@@ -0,0 +1,646 @@
+//! Round trips through the service API on small documents built in memory.
+
+use lopdf::content::{Content, Operation};
+use lopdf::encryption::Permissions;
+use lopdf::{dictionary, Document, Object, ObjectId, Stream};
+use slicepdf_lib::error::SliceError;
+use slicepdf_lib::services::image::conversion::images_to_pdf;
+use slicepdf_lib::services::job::Job;
+use slicepdf_lib::services::pdf::compress::{compress_pdf, keep_original, CompressMode};
+use slicepdf_lib::services::pdf::{
+    decrypt_pdf, load_document, merge_pdfs, protect_file, protect_pdf, rotate_pdf, Encryption,
+    MergeOptions, MergePageInstruction, Passwords, PdfPermissions, PrintPermission, ProtectOptions,
+    RotatePageInstructions,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use tempfile::TempDir;
+
+/// A letter-sized page whose content shows `label`, so pages can be told apart.
+fn add_page(doc: &mut Document, pages_id: ObjectId, label: &str) -> ObjectId {
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tj", vec![Object::string_literal(label)]),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+    doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => content_id,
+    })
+}
+
+/// Finishes `doc` with `kids` as its only pages.
+fn finish(mut doc: Document, pages_id: ObjectId, kids: Vec<ObjectId>) -> Document {
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids.into_iter().map(Object::Reference).collect::<Vec<_>>(),
+            "Count" => count,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc
+}
+
+/// A document of `count` pages labelled `"{name} 1"`, `"{name} 2"` and so on.
+fn labelled_pdf(name: &str, count: u32) -> Document {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let kids = (1..=count)
+        .map(|page| add_page(&mut doc, pages_id, &format!("{} {}", name, page)))
+        .collect();
+    finish(doc, pages_id, kids)
+}
+
+fn save(dir: &TempDir, name: &str, mut doc: Document) -> PathBuf {
+    let path = dir.path().join(name);
+    doc.save(&path).unwrap();
+    path
+}
+
+fn page_label(doc: &Document, page_id: ObjectId) -> String {
+    let content = doc.get_and_decode_page_content(page_id).unwrap();
+    content
+        .operations
+        .iter()
+        .find(|operation| operation.operator == "Tj")
+        .and_then(|operation| operation.operands.first())
+        .and_then(|label| label.as_str().ok())
+        .map(|label| String::from_utf8_lossy(label).to_string())
+        .unwrap()
+}
+
+fn page_labels(doc: &Document) -> Vec<String> {
+    doc.get_pages()
+        .values()
+        .map(|&page_id| page_label(doc, page_id))
+        .collect()
+}
+
+fn rotation(doc: &Document, page: u32) -> i64 {
+    let page_id = doc.get_pages()[&page];
+    doc.get_dictionary(page_id)
+        .unwrap()
+        .get(b"Rotate")
+        .and_then(Object::as_i64)
+        .unwrap_or(0)
+}
+
+fn protect_options(user_password: &str) -> ProtectOptions {
+    ProtectOptions {
+        user_password: user_password.to_string(),
+        owner_password: Some("owner".to_string()),
+        permissions: PdfPermissions::default(),
+        encryption: Default::default(),
+    }
+}
+
+#[test]
+fn merge_keeps_the_order_of_the_instructions() {
+    let dir = TempDir::new().unwrap();
+    let file_map = HashMap::from([
+        (
+            "a".to_string(),
+            save(&dir, "a.pdf", labelled_pdf("a", 2))
+                .display()
+                .to_string(),
+        ),
+        (
+            "b".to_string(),
+            save(&dir, "b.pdf", labelled_pdf("b", 3))
+                .display()
+                .to_string(),
+        ),
+    ]);
+    let instructions: Vec<MergePageInstruction> = [("b", 3), ("a", 1), ("b", 1), ("a", 2)]
+        .into_iter()
+        .map(|(file, page)| MergePageInstruction {
+            sourcepdfid: file.to_string(),
+            source_page_number: page,
+        })
+        .collect();
+
+    let mut output = Vec::new();
+    let report = merge_pdfs(
+        &instructions,
+        &file_map,
+        &Passwords::new(),
+        &MergeOptions::default(),
+        &mut output,
+        &Job::silent(),
+    )
+    .unwrap();
+
+    assert_eq!(report.pages, 4);
+    let merged = Document::load_mem(&output).unwrap();
+    assert_eq!(page_labels(&merged), ["b 3", "a 1", "b 1", "a 2"]);
+}
+
+#[test]
+fn merge_reports_missing_pages() {
+    let dir = TempDir::new().unwrap();
+    let path = save(&dir, "a.pdf", labelled_pdf("a", 2));
+    let file_map = HashMap::from([("a".to_string(), path.display().to_string())]);
+    let instructions = [MergePageInstruction {
+        sourcepdfid: "a".to_string(),
+        source_page_number: 3,
+    }];
+
+    let result = merge_pdfs(
+        &instructions,
+        &file_map,
+        &Passwords::new(),
+        &MergeOptions::default(),
+        &mut Vec::new(),
+        &Job::silent(),
+    );
+
+    assert!(matches!(
+        result,
+        Err(SliceError::PageOutOfRange {
+            page: 3,
+            page_count: 2,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rotate_adds_to_the_rotation_of_the_page() {
+    let dir = TempDir::new().unwrap();
+    let mut doc = labelled_pdf("a", 3);
+    let second = doc.get_pages()[&2];
+    doc.get_dictionary_mut(second).unwrap().set("Rotate", 270);
+    let path = save(&dir, "a.pdf", doc).display().to_string();
+    let instructions: Vec<RotatePageInstructions> = [(2, 180), (3, 90), (7, 90)]
+        .into_iter()
+        .map(|(pagenumber, rotation)| RotatePageInstructions {
+            pagenumber,
+            rotation,
+            filepath: path.clone(),
+        })
+        .collect();
+
+    let mut output = Vec::new();
+    let report = rotate_pdf(&instructions, None, &mut output, &Job::silent()).unwrap();
+
+    // Page 7 does not exist and is skipped
+    assert_eq!(report.pages, 2);
+    let rotated = Document::load_mem(&output).unwrap();
+    assert_eq!(rotation(&rotated, 1), 0);
+    assert_eq!(rotation(&rotated, 2), 90);
+    assert_eq!(rotation(&rotated, 3), 90);
+    assert_eq!(page_labels(&rotated), ["a 1", "a 2", "a 3"]);
+}
+
+/// A page showing a `pixels` wide square gradient one inch large, stored as raw RGB.
+fn image_pdf(pixels: u32) -> Document {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let samples: Vec<u8> = (0..pixels * pixels)
+        .flat_map(|i| {
+            let (x, y) = (i % pixels, i / pixels);
+            [(x * 255 / pixels) as u8, (y * 255 / pixels) as u8, 128]
+        })
+        .collect();
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => pixels as i64,
+            "Height" => pixels as i64,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+        },
+        samples,
+    ));
+    let content = Content {
+        operations: vec![
+            Operation::new("q", vec![]),
+            Operation::new(
+                "cm",
+                vec![
+                    72.into(),
+                    0.into(),
+                    0.into(),
+                    72.into(),
+                    100.into(),
+                    100.into(),
+                ],
+            ),
+            Operation::new("Do", vec!["Im1".into()]),
+            Operation::new("Q", vec![]),
+        ],
+    };
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Resources" => dictionary! { "XObject" => dictionary! { "Im1" => image_id } },
+        "Contents" => content_id,
+    });
+    finish(doc, pages_id, vec![page_id])
+}
+
+/// Width and filter of the image the only page of `doc` draws.
+fn drawn_image(doc: &Document) -> (i64, Vec<u8>) {
+    let page_id = doc.get_pages()[&1];
+    let (resources, _) = doc.get_page_resources(page_id).unwrap();
+    let image_id = resources
+        .unwrap()
+        .get(b"XObject")
+        .and_then(Object::as_dict)
+        .and_then(|xobjects| xobjects.get(b"Im1"))
+        .and_then(Object::as_reference)
+        .unwrap();
+    let image = doc
+        .get_object(image_id)
+        .and_then(Object::as_stream)
+        .unwrap();
+    let width = image.dict.get(b"Width").and_then(Object::as_i64).unwrap();
+    let filter = image
+        .dict
+        .get(b"Filter")
+        .and_then(Object::as_name)
+        .map(<[u8]>::to_vec)
+        .unwrap_or_default();
+    (width, filter)
+}
+
+#[test]
+fn compress_scales_images_down_to_the_maximum_dpi() {
+    let dir = TempDir::new().unwrap();
+    let path = save(&dir, "image.pdf", image_pdf(600));
+
+    let mut output = Vec::new();
+    let report = compress_pdf(
+        &path,
+        None,
+        &CompressMode::Ebook,
+        &mut output,
+        &Job::silent(),
+    )
+    .unwrap();
+
+    assert_eq!(report.images, 1);
+    assert!(!report.grew());
+    assert_eq!(report.size, output.len() as u64);
+    let compressed = Document::load_mem(&output).unwrap();
+    // Drawn one inch wide, at 150 DPI
+    assert_eq!(drawn_image(&compressed), (150, b"DCTDecode".to_vec()));
+}
+
+#[test]
+fn target_size_recompresses_images_that_need_no_scaling() {
+    // 48 pixels drawn one inch wide, below the DPI of every setting, as a noisy JPEG
+    let mut doc = image_pdf(48);
+    let mut state = 0x2545_f491_u32;
+    let noise = image::RgbImage::from_fn(48, 48, |_, _| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let [r, g, b, _] = state.to_le_bytes();
+        image::Rgb([r, g, b])
+    });
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 100)
+        .encode_image(&noise)
+        .unwrap();
+    for object in doc.objects.values_mut() {
+        if let Object::Stream(stream) = object {
+            if stream.dict.has(b"Width") {
+                stream.dict.set("Filter", "DCTDecode");
+                stream.set_content(jpeg.clone());
+            }
+        }
+    }
+    let dir = TempDir::new().unwrap();
+    let path = save(&dir, "photo.pdf", doc);
+    let original_size = std::fs::metadata(&path).unwrap().len();
+
+    let target = original_size * 3 / 4;
+    let mut output = Vec::new();
+    let report = compress_pdf(
+        &path,
+        None,
+        &CompressMode::TargetSize { bytes: target },
+        &mut output,
+        &Job::silent(),
+    )
+    .unwrap();
+
+    assert_eq!(report.images, 1);
+    assert!(report.size <= target, "{} > {}", report.size, target);
+    let compressed = Document::load_mem(&output).unwrap();
+    assert_eq!(drawn_image(&compressed), (48, b"DCTDecode".to_vec()));
+}
+
+#[test]
+fn lossless_compression_leaves_images_alone() {
+    let dir = TempDir::new().unwrap();
+    let path = save(&dir, "image.pdf", image_pdf(300));
+
+    let mut output = Vec::new();
+    let report = compress_pdf(
+        &path,
+        None,
+        &CompressMode::Lossless,
+        &mut output,
+        &Job::silent(),
+    )
+    .unwrap();
+
+    assert_eq!(report.images, 0);
+    let compressed = Document::load_mem(&output).unwrap();
+    assert_eq!(drawn_image(&compressed), (300, b"FlateDecode".to_vec()));
+}
+
+#[test]
+fn images_with_a_color_key_mask_are_left_alone() {
+    let dir = TempDir::new().unwrap();
+    let mut doc = image_pdf(600);
+    for object in doc.objects.values_mut() {
+        if let Object::Stream(stream) = object {
+            if stream.dict.has(b"Width") {
+                stream.dict.set(
+                    "Mask",
+                    vec![
+                        0.into(),
+                        10.into(),
+                        0.into(),
+                        10.into(),
+                        128.into(),
+                        128.into(),
+                    ],
+                );
+            }
+        }
+    }
+    let path = save(&dir, "image.pdf", doc);
+
+    let mut output = Vec::new();
+    let report = compress_pdf(
+        &path,
+        None,
+        &CompressMode::Ebook,
+        &mut output,
+        &Job::silent(),
+    )
+    .unwrap();
+
+    assert_eq!(report.images, 0);
+    let compressed = Document::load_mem(&output).unwrap();
+    assert_eq!(drawn_image(&compressed), (600, b"FlateDecode".to_vec()));
+}
+
+#[test]
+fn kept_originals_are_copied_unless_protected() {
+    let dir = TempDir::new().unwrap();
+    let path = save(&dir, "a.pdf", labelled_pdf("a", 2));
+    let mut copy = Vec::new();
+    assert!(!keep_original(&path, None, &mut copy, &Job::silent()).unwrap());
+    assert_eq!(copy, std::fs::read(&path).unwrap());
+
+    let protected = dir.path().join("protected.pdf");
+    let mut output = Vec::new();
+    protect_pdf(
+        &path,
+        &mut output,
+        &protect_options("secret"),
+        &Job::silent(),
+    )
+    .unwrap();
+    std::fs::write(&protected, &output).unwrap();
+    let mut kept = Vec::new();
+    assert!(keep_original(&protected, Some("secret"), &mut kept, &Job::silent()).unwrap());
+
+    let doc = Document::load_mem(&kept).unwrap();
+    assert!(!doc.trailer.has(b"Encrypt"));
+    assert_eq!(page_labels(&doc), ["a 1", "a 2"]);
+}
+
+#[test]
+fn protected_pdfs_open_with_the_password_only() {
+    let dir = TempDir::new().unwrap();
+    let path = save(&dir, "a.pdf", labelled_pdf("a", 2));
+    let protected = dir.path().join("protected.pdf");
+    let mut output = Vec::new();
+    protect_pdf(
+        &path,
+        &mut output,
+        &protect_options("secret"),
+        &Job::silent(),
+    )
+    .unwrap();
+    std::fs::write(&protected, &output).unwrap();
+
+    assert!(matches!(
+        load_document(&protected, None),
+        Err(SliceError::Encrypted { .. })
+    ));
+    assert!(matches!(
+        load_document(&protected, Some("wrong")),
+        Err(SliceError::WrongPassword { .. })
+    ));
+    let opened = load_document(&protected, Some("secret")).unwrap();
+    assert_eq!(page_labels(&opened), ["a 1", "a 2"]);
+}
+
+#[test]
+fn restricted_pdfs_without_a_password_open_with_their_permissions() {
+    let dir = TempDir::new().unwrap();
+    let path = save(&dir, "a.pdf", labelled_pdf("a", 1));
+    let options = ProtectOptions {
+        user_password: String::new(),
+        owner_password: Some("owner".to_string()),
+        permissions: PdfPermissions {
+            print: PrintPermission::LowQuality,
+            modify: false,
+            copy: false,
+            annotate: false,
+            fill_forms: true,
+            assemble: false,
+        },
+        encryption: Encryption::Aes256,
+    };
+    let restricted = dir.path().join("restricted.pdf");
+    let mut output = Vec::new();
+    protect_pdf(&path, &mut output, &options, &Job::silent()).unwrap();
+    std::fs::write(&restricted, &output).unwrap();
+
+    let opened = load_document(&restricted, None).unwrap();
+    assert_eq!(page_labels(&opened), ["a 1"]);
+    let state = opened.encryption_state.as_ref().unwrap();
+    assert_eq!(
+        state.permissions() & Permissions::all(),
+        Permissions::PRINTABLE | Permissions::FILLABLE | Permissions::COPYABLE_FOR_ACCESSIBILITY
+    );
+}
+
+#[test]
+fn aes_128_protection_uses_version_4_encryption() {
+    let dir = TempDir::new().unwrap();
+    let path = save(&dir, "a.pdf", labelled_pdf("a", 2));
+    let options = ProtectOptions {
+        encryption: Encryption::Aes128,
+        ..protect_options("secret")
+    };
+    let protected = dir.path().join("protected.pdf");
+    let mut output = Vec::new();
+    protect_pdf(&path, &mut output, &options, &Job::silent()).unwrap();
+    std::fs::write(&protected, &output).unwrap();
+
+    let opened = load_document(&protected, Some("secret")).unwrap();
+    assert_eq!(page_labels(&opened), ["a 1", "a 2"]);
+    assert_eq!(opened.encryption_state.as_ref().unwrap().version(), 4);
+}
+
+#[test]
+fn decrypting_a_protected_pdf_restores_it() {
+    let dir = TempDir::new().unwrap();
+    let path = save(&dir, "a.pdf", labelled_pdf("a", 3));
+    let protected = dir.path().join("protected.pdf");
+    let mut output = Vec::new();
+    protect_pdf(
+        &path,
+        &mut output,
+        &protect_options("secret"),
+        &Job::silent(),
+    )
+    .unwrap();
+    std::fs::write(&protected, &output).unwrap();
+
+    let mut decrypted = Vec::new();
+    decrypt_pdf(&protected, &mut decrypted, "secret", &Job::silent()).unwrap();
+
+    let doc = Document::load_mem(&decrypted).unwrap();
+    assert!(!doc.is_encrypted());
+    assert!(!doc.trailer.has(b"Encrypt"));
+    assert_eq!(page_labels(&doc), ["a 1", "a 2", "a 3"]);
+}
+
+/// A stand-in for qpdf that runs `script` with the output path in `$out`.
+#[cfg(unix)]
+fn fake_qpdf(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("for out; do :; done; {}", script))
+        .arg("qpdf");
+    command
+}
+
+#[cfg(unix)]
+#[test]
+fn qpdf_writes_the_output_only_once_it_succeeds() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("broken.pdf");
+    std::fs::write(&input, "not a PDF lopdf can read").unwrap();
+    let save_path = dir.path().join("protected.pdf");
+    std::fs::write(&save_path, "previous").unwrap();
+
+    let failed = protect_file(
+        &input,
+        &save_path,
+        &protect_options("secret"),
+        Some(fake_qpdf("echo partial > \"$out\"; exit 2")),
+        &Job::silent(),
+    );
+    assert!(matches!(failed, Err(SliceError::Internal { .. })));
+    assert_eq!(std::fs::read_to_string(&save_path).unwrap(), "previous");
+    assert!(!dir.path().join("protected.pdf.part").exists());
+
+    protect_file(
+        &input,
+        &save_path,
+        &protect_options("secret"),
+        Some(fake_qpdf("echo encrypted > \"$out\"")),
+        &Job::silent(),
+    )
+    .unwrap();
+    assert_eq!(std::fs::read_to_string(&save_path).unwrap(), "encrypted\n");
+    assert!(!dir.path().join("protected.pdf.part").exists());
+}
+
+#[test]
+fn decrypting_an_unprotected_pdf_fails() {
+    let dir = TempDir::new().unwrap();
+    let path = save(&dir, "a.pdf", labelled_pdf("a", 1));
+
+    let result = decrypt_pdf(&path, &mut Vec::new(), "", &Job::silent());
+
+    assert!(matches!(result, Err(SliceError::InvalidInput { .. })));
+}
+
+/// Numbers among `operands`, as drawn.
+fn numbers(operands: &[Object]) -> Vec<f32> {
+    operands.iter().map(|n| n.as_float().unwrap()).collect()
+}
+
+#[test]
+fn images_become_a4_pages_with_the_image_fitted_inside_the_margins() {
+    let dir = TempDir::new().unwrap();
+    let wide = dir.path().join("wide.png");
+    image::RgbImage::from_pixel(400, 200, image::Rgb([200, 40, 40]))
+        .save(&wide)
+        .unwrap();
+    let tall = dir.path().join("tall.png");
+    image::RgbImage::from_pixel(100, 300, image::Rgb([40, 40, 200]))
+        .save(&tall)
+        .unwrap();
+
+    let mut output = Vec::new();
+    images_to_pdf(&[wide, tall], &mut output, &Job::silent()).unwrap();
+
+    let doc = Document::load_mem(&output).unwrap();
+    let pages = doc.get_pages();
+    assert_eq!(pages.len(), 2);
+    let margin = 20.0 / 25.4 * 72.0;
+    // Both stay portrait; the wide image fills the width, the tall one the height
+    let expected = [
+        (595.0 - 2.0 * margin, (595.0 - 2.0 * margin) / 2.0),
+        ((842.0 - 2.0 * margin) / 3.0, 842.0 - 2.0 * margin),
+    ];
+    for (page_id, (width, height)) in pages.values().zip(expected) {
+        let page = doc.get_dictionary(*page_id).unwrap();
+        let media_box = numbers(page.get(b"MediaBox").and_then(Object::as_array).unwrap());
+        assert_eq!(media_box, [0.0, 0.0, 595.0, 842.0]);
+
+        let content = Content::decode(&doc.get_page_content(*page_id).unwrap()).unwrap();
+        let cm = content
+            .operations
+            .iter()
+            .find(|operation| operation.operator == "cm")
+            .unwrap();
+        let cm = numbers(&cm.operands);
+        let centered = [
+            width,
+            0.0,
+            0.0,
+            height,
+            (595.0 - width) / 2.0,
+            (842.0 - height) / 2.0,
+        ];
+        for (actual, expected) in cm.iter().zip(centered) {
+            assert!(
+                (actual - expected).abs() < 0.01,
+                "{:?} != {:?}",
+                cm,
+                centered
+            );
+        }
+    }
+}
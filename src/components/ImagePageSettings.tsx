@@ -0,0 +1,165 @@
+import {
+  FitMode,
+  ImagePageOptions,
+  Orientation,
+  PaperSize,
+} from "../types/pdf";
+
+const POINTS_PER_MM = 72 / 25.4;
+
+const toPoints = (mm: number) => mm * POINTS_PER_MM;
+const toMm = (points: number) => Math.round(points / POINTS_PER_MM);
+
+export const defaultImagePageOptions: ImagePageOptions = {
+  paper: { kind: "a4" },
+  orientation: "auto",
+  margin: 0,
+  fit: "fit",
+  maxDpi: 150,
+};
+
+const paperOptions: { kind: PaperSize["kind"]; label: string }[] = [
+  { kind: "a4", label: "A4" },
+  { kind: "letter", label: "Letter" },
+  { kind: "legal", label: "Legal" },
+  { kind: "a3", label: "A3" },
+  { kind: "custom", label: "Custom" },
+  { kind: "sameAsImage", label: "Same as image" },
+];
+
+const inputClass =
+  "w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500";
+const labelClass = "text-xs font-bold text-gray-400 uppercase";
+
+type Props = {
+  value: ImagePageOptions;
+  onChange: (value: ImagePageOptions) => void;
+};
+
+const ImagePageSettings = ({ value, onChange }: Props) => {
+  const setPaper = (kind: PaperSize["kind"]) => {
+    onChange({
+      ...value,
+      paper:
+        kind === "custom"
+          ? { kind, width: toPoints(210), height: toPoints(297) }
+          : { kind },
+    });
+  };
+
+  return (
+    <div className="w-full grid grid-cols-2 lg:grid-cols-5 gap-4 mb-4 px-2">
+      <div className="space-y-2">
+        <label className={labelClass}>Page size</label>
+        <select
+          value={value.paper.kind}
+          onChange={(e) => setPaper(e.target.value as PaperSize["kind"])}
+          className={inputClass}
+        >
+          {paperOptions.map((option) => (
+            <option key={option.kind} value={option.kind}>
+              {option.label}
+            </option>
+          ))}
+        </select>
+      </div>
+      <div className="space-y-2">
+        <label className={labelClass}>Orientation</label>
+        <select
+          value={value.orientation}
+          disabled={value.paper.kind === "sameAsImage"}
+          onChange={(e) =>
+            onChange({ ...value, orientation: e.target.value as Orientation })
+          }
+          className={inputClass + " disabled:opacity-50"}
+        >
+          <option value="auto">Match image</option>
+          <option value="portrait">Portrait</option>
+          <option value="landscape">Landscape</option>
+        </select>
+      </div>
+      <div className="space-y-2">
+        <label className={labelClass}>Image</label>
+        <select
+          value={value.fit}
+          onChange={(e) =>
+            onChange({ ...value, fit: e.target.value as FitMode })
+          }
+          className={inputClass}
+        >
+          <option value="fit">Fit whole image</option>
+          <option value="fill">Fill page and crop</option>
+          <option value="stretch">Stretch</option>
+        </select>
+      </div>
+      <div className="space-y-2">
+        <label className={labelClass}>Margins (mm)</label>
+        <input
+          type="number"
+          min={0}
+          value={toMm(value.margin)}
+          onChange={(e) =>
+            onChange({ ...value, margin: toPoints(Number(e.target.value)) })
+          }
+          className={inputClass}
+        />
+      </div>
+      <div className="space-y-2">
+        <label className={labelClass}>Max DPI</label>
+        <input
+          type="number"
+          min={1}
+          value={value.maxDpi}
+          onChange={(e) =>
+            onChange({ ...value, maxDpi: Number(e.target.value) })
+          }
+          className={inputClass}
+        />
+      </div>
+      {value.paper.kind === "custom" && (
+        <>
+          <div className="space-y-2">
+            <label className={labelClass}>Width (mm)</label>
+            <input
+              type="number"
+              min={1}
+              value={toMm(value.paper.width)}
+              onChange={(e) =>
+                value.paper.kind === "custom" &&
+                onChange({
+                  ...value,
+                  paper: {
+                    ...value.paper,
+                    width: toPoints(Number(e.target.value)),
+                  },
+                })
+              }
+              className={inputClass}
+            />
+          </div>
+          <div className="space-y-2">
+            <label className={labelClass}>Height (mm)</label>
+            <input
+              type="number"
+              min={1}
+              value={toMm(value.paper.height)}
+              onChange={(e) =>
+                value.paper.kind === "custom" &&
+                onChange({
+                  ...value,
+                  paper: {
+                    ...value.paper,
+                    height: toPoints(Number(e.target.value)),
+                  },
+                })
+              }
+              className={inputClass}
+            />
+          </div>
+        </>
+      )}
+    </div>
+  );
+};
+
+export default ImagePageSettings;
@@ -1,8 +1,21 @@
+import { ProgressEvent } from "../types/jobs";
+
 type SlantedLoaderProps = {
   label?: string;
+  progress?: ProgressEvent | null;
+  onCancel?: () => void;
 };
 
-const Loader = ({ label = "Processing..." }: SlantedLoaderProps) => {
+const Loader = ({
+  label = "Processing...",
+  progress,
+  onCancel,
+}: SlantedLoaderProps) => {
+  const percent =
+    progress && progress.total > 0
+      ? Math.round((progress.done / progress.total) * 100)
+      : null;
+
   return (
     <div className="fixed inset-0 z-9999 flex flex-col items-center justify-center bg-white/80 backdrop-blur-md">
       {/* Label placed above the animation */}
@@ -17,6 +30,31 @@ const Loader = ({ label = "Processing..." }: SlantedLoaderProps) => {
         <div className="w-4 h-4 bg-blue-600 rounded-full animate-bounce"></div>
       </div>
 
+      {/* Real progress, when the running job reports it */}
+      {progress && percent !== null && (
+        <div className="mt-6 w-64">
+          <div className="h-2 w-full overflow-hidden rounded-full bg-gray-200">
+            <div
+              className="h-full bg-blue-600 transition-all"
+              style={{ width: `${percent}%` }}
+            ></div>
+          </div>
+          <p className="mt-2 truncate text-center text-xs text-gray-500">
+            {progress.step} {progress.done}/{progress.total}
+            {progress.current ? ` · ${progress.current}` : ""}
+          </p>
+        </div>
+      )}
+
+      {onCancel && (
+        <button
+          onClick={onCancel}
+          className="mt-6 rounded-md border border-gray-300 px-4 py-1.5 text-xs font-semibold tracking-widest text-gray-600 uppercase hover:bg-gray-100"
+        >
+          Cancel
+        </button>
+      )}
+
       {/* Optional: Subtle glow effect behind the dots */}
       <div className="absolute w-32 h-32 bg-blue-400/20 rounded-full blur-3xl -z-10"></div>
     </div>
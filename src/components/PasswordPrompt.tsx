@@ -0,0 +1,63 @@
+import { useState } from "react";
+
+type PasswordPromptProps = {
+  fileName: string;
+  retry: boolean;
+  onSubmit: (password: string) => void;
+  onCancel: () => void;
+};
+
+const PasswordPrompt = ({
+  fileName,
+  retry,
+  onSubmit,
+  onCancel,
+}: PasswordPromptProps) => {
+  const [password, setPassword] = useState("");
+
+  return (
+    <div className="fixed inset-0 z-9999 flex items-center justify-center bg-white/80 backdrop-blur-md">
+      <form
+        onSubmit={(e) => {
+          e.preventDefault();
+          onSubmit(password);
+        }}
+        className="flex w-96 flex-col gap-4 rounded-xl border border-border bg-white p-6 shadow-lg"
+      >
+        <div>
+          <p className="font-bold text-gray-700">Password required</p>
+          <p className="truncate text-sm text-gray-500">{fileName}</p>
+        </div>
+        <input
+          autoFocus
+          type="password"
+          value={password}
+          onChange={(e) => setPassword(e.target.value)}
+          className="w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500"
+        />
+        {retry && (
+          <p className="text-xs text-red-500">
+            That password is not correct, try again.
+          </p>
+        )}
+        <div className="flex gap-3">
+          <button
+            type="submit"
+            className="flex-1 py-3 bg-gray-900 text-white font-bold rounded-xl hover:bg-gray-800"
+          >
+            Unlock
+          </button>
+          <button
+            type="button"
+            onClick={onCancel}
+            className="flex-1 py-3 font-semibold rounded-xl border border-red-300"
+          >
+            Skip file
+          </button>
+        </div>
+      </form>
+    </div>
+  );
+};
+
+export default PasswordPrompt;
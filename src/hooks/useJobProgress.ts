@@ -0,0 +1,27 @@
+import { useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { ProgressEvent } from "../types/jobs";
+
+export const PROGRESS_EVENT = "job://progress";
+
+// Latest progress update for `jobId`, or null while nothing has been reported.
+export function useJobProgress(jobId: string | null) {
+  const [progress, setProgress] = useState<ProgressEvent | null>(null);
+
+  useEffect(() => {
+    setProgress(null);
+    if (!jobId) return;
+
+    const unlisten = listen<ProgressEvent>(PROGRESS_EVENT, (event) => {
+      if (event.payload.jobId === jobId) {
+        setProgress(event.payload);
+      }
+    });
+
+    return () => {
+      unlisten.then((off) => off());
+    };
+  }, [jobId]);
+
+  return progress;
+}
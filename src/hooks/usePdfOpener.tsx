@@ -0,0 +1,68 @@
+import { useState } from "react";
+import * as pdfJs from "pdfjs-dist";
+import { readFile } from "@tauri-apps/plugin-fs";
+import PasswordPrompt from "../components/PasswordPrompt";
+
+export type OpenedPdf = {
+  pdf: pdfJs.PDFDocumentProxy;
+  // Set when the file is encrypted, the backend needs it to read the file too.
+  password?: string;
+};
+
+type PasswordRequest = {
+  fileName: string;
+  retry: boolean;
+  resolve: (password: string | null) => void;
+};
+
+// Opens PDFs with pdf.js and asks for a password when a file needs one.
+// Render `passwordPrompt` somewhere in the page.
+export function usePdfOpener() {
+  const [request, setRequest] = useState<PasswordRequest | null>(null);
+
+  const askPassword = (fileName: string, retry: boolean) =>
+    new Promise<string | null>((resolve) =>
+      setRequest({ fileName, retry, resolve }),
+    );
+
+  // Resolves to null when the user skips the password prompt.
+  const openPdf = async (path: string): Promise<OpenedPdf | null> => {
+    const bytes = await readFile(path);
+    const fileName = path.split(/[\\/]/).pop() ?? "Unknown.pdf";
+    let password: string | undefined;
+
+    for (;;) {
+      try {
+        // pdf.js takes ownership of the buffer, hand it a copy so we can retry.
+        const pdf = await pdfJs.getDocument({ data: bytes.slice(), password })
+          .promise;
+        return { pdf, password };
+      } catch (err: any) {
+        if (err?.name !== "PasswordException") throw err;
+        const answer = await askPassword(
+          fileName,
+          err.code === pdfJs.PasswordResponses.INCORRECT_PASSWORD,
+        );
+        if (answer === null) return null;
+        password = answer;
+      }
+    }
+  };
+
+  const answer = (password: string | null) => {
+    request?.resolve(password);
+    setRequest(null);
+  };
+
+  const passwordPrompt = request && (
+    <PasswordPrompt
+      key={`${request.fileName}-${request.retry}`}
+      fileName={request.fileName}
+      retry={request.retry}
+      onSubmit={(password) => answer(password)}
+      onCancel={() => answer(null)}
+    />
+  );
+
+  return { openPdf, passwordPrompt };
+}
@@ -11,6 +11,9 @@ import CompressPdf from "./pages/CompressPdf";
 import RotatePdfPages from "./pages/RotatePdfPages";
 import ResizeImage from "./pages/ResizeImage";
 import CompressImage from "./pages/compressImage";
+import ProtectPdf from "./pages/ProtectPdf";
+import UnlockPdf from "./pages/UnlockPdf";
+import SplitPdf from "./pages/SplitPdf";
 import MainLayout from "./layout";
 import ErrorPage from "./pages/ErrorPage";
 
@@ -35,6 +38,9 @@ const router = createBrowserRouter([
       { path: "/rotate-pdf", element: <RotatePdfPages /> },
       { path: "/resize-image", element: <ResizeImage /> },
       { path: "/compress-image", element: <CompressImage /> },
+      { path: "/protect-pdf", element: <ProtectPdf /> },
+      { path: "/unlock-pdf", element: <UnlockPdf /> },
+      { path: "/split-pdf", element: <SplitPdf /> },
     ],
   },
 ]);
@@ -5,13 +5,57 @@ import { message, open } from "@tauri-apps/plugin-dialog";
 import { invoke } from "@tauri-apps/api/core";
 import { useState } from "react";
 import Loader from "../components/Loading";
+import { useJobProgress } from "../hooks/useJobProgress";
+import { usePdfOpener } from "../hooks/usePdfOpener";
 import SelectedFileCard from "../components/SelectedPdfCard";
 import NoFilesYet from "../components/NoFilesYet";
+import { isCancelled, isSliceError } from "../types/errors";
+import { CompressMode, CompressOptions } from "../types/pdf";
+
+const inputClass =
+  "w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500";
+const labelClass = "text-xs font-bold text-gray-400 uppercase";
+
+const modeOptions: { kind: CompressMode["kind"]; label: string }[] = [
+  { kind: "screen", label: "Screen (72 DPI)" },
+  { kind: "ebook", label: "Ebook (150 DPI)" },
+  { kind: "print", label: "Print (300 DPI)" },
+  { kind: "lossless", label: "Lossless" },
+  { kind: "custom", label: "Custom" },
+  { kind: "targetSize", label: "Target size" },
+];
 
 const CompressPdf = () => {
   const [loading, setLoading] = useState(false);
+  const [jobId, setJobId] = useState<string | null>(null);
+  const progress = useJobProgress(jobId);
   const [inputPath, setInputPath] = useState<string | null>();
   const [fileName, setFileName] = useState<string>("Unknown.pdf");
+  const [password, setPassword] = useState<string>();
+  const [kind, setKind] = useState<CompressMode["kind"]>("ebook");
+  const [options, setOptions] = useState<CompressOptions>({
+    maxDpi: 150,
+    quality: 75,
+  });
+  const [targetValue, setTargetValue] = useState<number>(1);
+  const [targetUnit, setTargetUnit] = useState<"KB" | "MB">("MB");
+
+  const compressMode = (): CompressMode => {
+    switch (kind) {
+      case "custom":
+        return { kind, ...options };
+      case "targetSize":
+        return {
+          kind,
+          bytes: Math.round(
+            targetValue * (targetUnit === "MB" ? 1024 * 1024 : 1024),
+          ),
+        };
+      default:
+        return { kind };
+    }
+  };
+  const { openPdf, passwordPrompt } = usePdfOpener();
   const openFilePicker = async () => {
     const path = await takePath();
     if (!path) {
@@ -20,6 +64,11 @@ const CompressPdf = () => {
       message("invalid path");
       return;
     }
+    // Only opened to find out whether a password is needed.
+    const opened = await openPdf(path).catch(() => null);
+    if (!opened) return;
+    opened.pdf.destroy();
+    setPassword(opened.password);
     setInputPath(path);
     setFileName(path.split(/[\\/]/).pop() ?? "Unknown.pdf");
   };
@@ -43,17 +92,26 @@ const CompressPdf = () => {
   const clearPdf = () => {
     setInputPath(null);
     setFileName("");
+    setPassword(undefined);
   };
 
   const compressPdf = async () => {
     try {
+      const currentJob = crypto.randomUUID();
+      setJobId(currentJob);
       setLoading(true);
       const result = await invoke("compress_pdf", {
         inputPath,
+        password,
+        mode: compressMode(),
+        jobId: currentJob,
       });
       alert(result);
     } catch (error) {
       setLoading(false);
+      if (!isCancelled(error)) {
+        message("Error: " + (isSliceError(error) ? error.message : error));
+      }
     } finally {
       setLoading(false);
     }
@@ -68,7 +126,14 @@ const CompressPdf = () => {
   return (
     <>
       <div className="w-full h-full lg:px-50 lg:py-30 p-10">
-        {loading && <Loader label="compressing" />}
+        {passwordPrompt}
+        {loading && (
+          <Loader
+            label="compressing"
+            progress={progress}
+            onCancel={() => invoke("cancel_job", { jobId })}
+          />
+        )}
         <div className="w-full flex flex-col items-center justify-center gap-5">
           <ToolCard
             hide={true}
@@ -95,7 +160,83 @@ const CompressPdf = () => {
           ) : (
             <>
               <SelectedFileCard fileName={fileName} />
-
+              <div className="w-full grid grid-cols-2 lg:grid-cols-3 gap-4 px-2">
+                <div className="space-y-2">
+                  <label className={labelClass}>Compression</label>
+                  <select
+                    value={kind}
+                    onChange={(e) =>
+                      setKind(e.target.value as CompressMode["kind"])
+                    }
+                    className={inputClass}
+                  >
+                    {modeOptions.map((option) => (
+                      <option key={option.kind} value={option.kind}>
+                        {option.label}
+                      </option>
+                    ))}
+                  </select>
+                </div>
+                {kind === "custom" && (
+                  <>
+                    <div className="space-y-2">
+                      <label className={labelClass}>Max DPI</label>
+                      <input
+                        type="number"
+                        min={1}
+                        value={options.maxDpi}
+                        onChange={(e) =>
+                          setOptions({
+                            ...options,
+                            maxDpi: Number(e.target.value),
+                          })
+                        }
+                        className={inputClass}
+                      />
+                    </div>
+                    <div className="space-y-2">
+                      <label className={labelClass}>Quality</label>
+                      <input
+                        type="number"
+                        min={1}
+                        max={100}
+                        value={options.quality}
+                        onChange={(e) =>
+                          setOptions({
+                            ...options,
+                            quality: Number(e.target.value),
+                          })
+                        }
+                        className={inputClass}
+                      />
+                    </div>
+                  </>
+                )}
+                {kind === "targetSize" && (
+                  <div className="space-y-2">
+                    <label className={labelClass}>Target size</label>
+                    <div className="flex gap-3">
+                      <input
+                        type="number"
+                        min={1}
+                        value={targetValue}
+                        onChange={(e) => setTargetValue(Number(e.target.value))}
+                        className={inputClass}
+                      />
+                      <select
+                        value={targetUnit}
+                        onChange={(e) =>
+                          setTargetUnit(e.target.value as "KB" | "MB")
+                        }
+                        className={inputClass + " w-auto"}
+                      >
+                        <option value="KB">KB</option>
+                        <option value="MB">MB</option>
+                      </select>
+                    </div>
+                  </div>
+                )}
+              </div>
               <button
                 disabled={inputPath == null}
                 type="button"
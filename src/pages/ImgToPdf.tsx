@@ -6,6 +6,8 @@ import { readFile } from "@tauri-apps/plugin-fs";
 import { useState, useEffect } from "react";
 import NoFilesYet from "../components/NoFilesYet";
 import Loader from "../components/Loading";
+import { useJobProgress } from "../hooks/useJobProgress";
+import { isCancelled, isSliceError } from "../types/errors";
 
 interface ImagePreview {
   path: string;
@@ -16,6 +18,8 @@ interface ImagePreview {
 const ImageToPdf = () => {
   const [previews, setPreviews] = useState<ImagePreview[]>([]);
   const [loading, setLoading] = useState<boolean>(false);
+  const [jobId, setJobId] = useState<string | null>(null);
+  const progress = useJobProgress(jobId);
 
   // Helper to clear URLs from memory
   const revokeUrls = (items: ImagePreview[]) => {
@@ -76,6 +80,8 @@ const ImageToPdf = () => {
   const handleConvert = async () => {
     if (previews.length === 0) return;
 
+    const currentJob = crypto.randomUUID();
+    setJobId(currentJob);
     setLoading(true);
     try {
       let result: string;
@@ -85,24 +91,26 @@ const ImageToPdf = () => {
         result = await invoke("convert_image_to_pdf", {
           inputPath: previews[0].path,
           fileName: previews[0].name,
+          jobId: currentJob,
         });
       } else {
         // Rust expects: input_paths: Vec<String>, file_name: String
         result = await invoke("convert_images_to_pdf", {
           inputPaths: previews.map((p) => p.path),
           fileName: "merged_images.pdf", // Default name for the dialog
+          jobId: currentJob,
         });
       }
 
       message(result); // This will show the "Success: PDF created..." message from Rust
       clearAll();
     } catch (e) {
-      // If the user cancels the dialog, Rust returns "Save cancelled" as an Err
-      if (e !== "Save cancelled") {
-        message("Error: " + e);
+      if (!isCancelled(e)) {
+        message("Error: " + (isSliceError(e) ? e.message : e));
       }
     } finally {
       setLoading(false);
+      setJobId(null);
     }
   };
 
@@ -115,7 +123,13 @@ const ImageToPdf = () => {
 
   return (
     <div className="w-full h-full lg:px-50 lg:py-10 p-10">
-      {loading && <Loader label="Processing Images..." />}
+      {loading && (
+        <Loader
+          label="Processing Images..."
+          progress={progress}
+          onCancel={jobId ? () => invoke("cancel_job", { jobId }) : undefined}
+        />
+      )}
 
       <div className="flex flex-col gap-6 max-w-4xl mx-auto">
         <ToolCard hide={true} {...tool!} />
@@ -10,6 +10,13 @@ import { ToolCard } from "../components/ToolCard";
 import { Tool } from "../types/tools";
 import toolData from "../data/tools.json";
 import Loader from "../components/Loading";
+import { useJobProgress } from "../hooks/useJobProgress";
+import { usePdfOpener } from "../hooks/usePdfOpener";
+import { isCancelled, isSliceError } from "../types/errors";
+import { ImagePageOptions, MergeOptions, PageSource } from "../types/pdf";
+import ImagePageSettings, {
+  defaultImagePageOptions,
+} from "../components/ImagePageSettings";
 
 pdfJs.GlobalWorkerOptions.workerSrc = workerSrc;
 
@@ -21,6 +28,7 @@ type SourceFileMap = Record<
     type: "pdf" | "image";
     pdf?: pdfJs.PDFDocumentProxy;
     imageUrl?: string;
+    password?: string;
   }
 >;
 
@@ -35,6 +43,13 @@ type DraggablePage = {
 const MergeImageWithPdf = () => {
   const [sourceFiles, setSourceFiles] = useState<SourceFileMap>({});
   const [loading, setLoading] = useState(false);
+  const [jobId, setJobId] = useState<string | null>(null);
+  const [bookmarkSources, setBookmarkSources] = useState(false);
+  const [imagePages, setImagePages] = useState<ImagePageOptions>(
+    defaultImagePageOptions,
+  );
+  const progress = useJobProgress(jobId);
+  const { openPdf, passwordPrompt } = usePdfOpener();
 
   const [parent, pages, setPages] = useDragAndDrop<
     HTMLDivElement,
@@ -61,13 +76,15 @@ const MergeImageWithPdf = () => {
       const fileName = path.split(/[\\/]/).pop() ?? "file";
 
       if (mode === "pdf") {
-        const bytes = await readFile(activePath);
-        const pdf = await pdfJs.getDocument({ data: bytes }).promise;
+        const opened = await openPdf(activePath);
+        if (!opened) continue;
+        const { pdf, password } = opened;
         newSources[fileId] = {
           filename: fileName,
           filepath: activePath,
           type: "pdf",
           pdf,
+          password,
         };
 
         for (let i = 1; i <= pdf.numPages; i++) {
@@ -110,24 +127,38 @@ const MergeImageWithPdf = () => {
 
   const runMerge = async () => {
     const fileMap: Record<string, string> = {};
-    Object.entries(sourceFiles).forEach(
-      ([id, data]) => (fileMap[id] = data.filepath),
-    );
+    const passwords: Record<string, string> = {};
+    Object.entries(sourceFiles).forEach(([id, data]) => {
+      fileMap[id] = data.filepath;
+      if (data.password !== undefined) {
+        passwords[id] = data.password;
+      }
+    });
 
-    const instructions = pages
+    const instructions: PageSource[] = pages
       .filter((p) => p.selected)
-      .map((p) => ({
-        fileId: p.fileId,
-        pageNumber: p.pageNumber,
-        kind: p.type,
-      }));
+      .map((p) =>
+        p.type === "pdf"
+          ? { kind: "pdf", fileId: p.fileId, pageNumber: p.pageNumber }
+          : { kind: "image", fileId: p.fileId },
+      );
+    const currentJob = crypto.randomUUID();
+    setJobId(currentJob);
     setLoading(true);
     try {
-      await invoke("merge_all", { instructions, fileMap });
+      await invoke("merge_all", {
+        instructions,
+        fileMap,
+        passwords,
+        options: { bookmarkSources, imagePages } satisfies MergeOptions,
+        jobId: currentJob,
+      });
       message("Successfully merged!");
     } catch (e) {
       setLoading(false);
-      message("Error: " + e);
+      if (!isCancelled(e)) {
+        message("Error: " + (isSliceError(e) ? e.message : e));
+      }
     } finally {
       setLoading(false);
     }
@@ -147,7 +178,14 @@ const MergeImageWithPdf = () => {
 
   return (
     <div className="w-full h-full lg:px-50 lg:py-30 p-10">
-      {loading && <Loader label="Merging files" />}
+      {passwordPrompt}
+      {loading && (
+        <Loader
+          label="Merging files"
+          progress={progress}
+          onCancel={() => invoke("cancel_job", { jobId })}
+        />
+      )}
       <div className="w-full flex flex-col items-center justify-center gap-5">
         <ToolCard
           hide={true}
@@ -193,6 +231,24 @@ const MergeImageWithPdf = () => {
         </button>
       </div>
 
+      {pages.length > 0 && (
+        <label className="flex items-center gap-3 mb-4 px-2 cursor-pointer">
+          <input
+            type="checkbox"
+            checked={bookmarkSources}
+            onChange={(e) => setBookmarkSources(e.target.checked)}
+            className="accent-blue-600"
+          />
+          <span className="font-medium">
+            Add a bookmark for each file and image
+          </span>
+        </label>
+      )}
+
+      {pages.some((p) => p.type === "image") && (
+        <ImagePageSettings value={imagePages} onChange={setImagePages} />
+      )}
+
       {pages.length > 0 ? (
         <div
           ref={parent}
@@ -5,13 +5,16 @@ import { message, open } from "@tauri-apps/plugin-dialog";
 import * as pdfJs from "pdfjs-dist";
 import { useEffect, useRef, useState } from "react";
 import workerSrc from "pdfjs-dist/build/pdf.worker.min.mjs?url";
-import { readFile } from "@tauri-apps/plugin-fs";
 import { useDragAndDrop } from "@formkit/drag-and-drop/react";
 import { animations } from "@formkit/drag-and-drop";
 import { invoke } from "@tauri-apps/api/core";
 import NoFilesYet from "../components/NoFilesYet";
 import Loader from "../components/Loading";
+import { useJobProgress } from "../hooks/useJobProgress";
+import { usePdfOpener } from "../hooks/usePdfOpener";
+import { isCancelled, isSliceError } from "../types/errors";
 import { Move } from "lucide-react";
+import { MergeOptions } from "../types/pdf";
 pdfJs.GlobalWorkerOptions.workerSrc = workerSrc;
 
 type SourcePdfMap = Record<
@@ -20,6 +23,7 @@ type SourcePdfMap = Record<
     filename: string;
     pdf: pdfJs.PDFDocumentProxy;
     filepath: string;
+    password?: string;
   }
 >;
 
@@ -33,6 +37,10 @@ type DraggablePage = {
 const MergePdf = () => {
   const [sourcePdfs, setSourcePdfs] = useState<SourcePdfMap>({});
   const [loading, setLoading] = useState<boolean>(false);
+  const [jobId, setJobId] = useState<string | null>(null);
+  const [bookmarkSources, setBookmarkSources] = useState(false);
+  const progress = useJobProgress(jobId);
+  const { openPdf, passwordPrompt } = usePdfOpener();
   const [parent, pages, setPages] = useDragAndDrop<
     HTMLDivElement,
     DraggablePage
@@ -50,8 +58,9 @@ const MergePdf = () => {
       let activePath = path;
 
       try {
-        const bytes = await readFile(activePath);
-        const pdf = await pdfJs.getDocument({ data: bytes }).promise;
+        const opened = await openPdf(activePath);
+        if (!opened) continue;
+        const { pdf, password } = opened;
         const fileName = path.split(/[\\/]/).pop() ?? "Unknown.pdf";
         const pdfId = crypto.randomUUID();
 
@@ -59,6 +68,7 @@ const MergePdf = () => {
           filename: fileName,
           pdf,
           filepath: activePath, // Store the path that the backend should use
+          password,
         };
 
         for (let i = 1; i <= pdf.numPages; i++) {
@@ -90,8 +100,12 @@ const MergePdf = () => {
 
   const mergePdfs = async () => {
     const pdfMapping: Record<string, string> = {};
+    const passwords: Record<string, string> = {};
     for (const [id, data] of Object.entries(sourcePdfs)) {
       pdfMapping[id] = data.filepath;
+      if (data.password !== undefined) {
+        passwords[id] = data.password;
+      }
     }
 
     const processingPayload = pages
@@ -102,17 +116,26 @@ const MergePdf = () => {
       }));
 
     try {
+      const currentJob = crypto.randomUUID();
+      setJobId(currentJob);
       setLoading(true);
       const result = await invoke("merge_pdf", {
         instructions: processingPayload,
         fileMap: pdfMapping,
+        passwords,
+        options: { bookmarkSources } satisfies MergeOptions,
+        jobId: currentJob,
       });
       setLoading(false);
       message(String(result));
       clearPdfs();
     } catch (error) {
       setLoading(false);
-      clearPdfs();
+      // A cancelled merge keeps the selection so it can be started again.
+      if (!isCancelled(error)) {
+        message("Error: " + (isSliceError(error) ? error.message : error));
+        clearPdfs();
+      }
     }
   };
 
@@ -133,7 +156,14 @@ const MergePdf = () => {
 
   return (
     <div className="w-full h-full lg:px-50 lg:py-30 p-10">
-      {loading && <Loader label="merging" />}
+      {passwordPrompt}
+      {loading && (
+        <Loader
+          label="merging"
+          progress={progress}
+          onCancel={() => invoke("cancel_job", { jobId })}
+        />
+      )}
       <div className="w-full flex flex-col items-center justify-center gap-5">
         <ToolCard
           hide={true}
@@ -175,6 +205,18 @@ const MergePdf = () => {
         </button>
       </div>
 
+      {pages.length > 0 && (
+        <label className="flex items-center gap-3 mb-4 px-2 cursor-pointer">
+          <input
+            type="checkbox"
+            checked={bookmarkSources}
+            onChange={(e) => setBookmarkSources(e.target.checked)}
+            className="accent-blue-600"
+          />
+          <span className="font-medium">Add a bookmark for each file</span>
+        </label>
+      )}
+
       {pages.length > 0 && (
         <div
           ref={parent}
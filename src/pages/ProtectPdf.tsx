@@ -0,0 +1,268 @@
+import { ToolCard } from "../components/ToolCard";
+import { Tool } from "../types/tools";
+import toolData from "../data/tools.json";
+import { message, open } from "@tauri-apps/plugin-dialog";
+import { invoke } from "@tauri-apps/api/core";
+import { useState } from "react";
+import Loader from "../components/Loading";
+import { useJobProgress } from "../hooks/useJobProgress";
+import SelectedFileCard from "../components/SelectedPdfCard";
+import NoFilesYet from "../components/NoFilesYet";
+import { isCancelled, isSliceError } from "../types/errors";
+import {
+  Encryption,
+  PdfPermissions,
+  PdfResult,
+  pdfResultText,
+  PrintPermission,
+} from "../types/pdf";
+
+const allowAll: PdfPermissions = {
+  print: "highQuality",
+  modify: true,
+  copy: true,
+  annotate: true,
+  fillForms: true,
+  assemble: true,
+};
+
+const permissionOptions: {
+  key: keyof Omit<PdfPermissions, "print">;
+  label: string;
+}[] = [
+  { key: "modify", label: "Edit content" },
+  { key: "copy", label: "Copy text and images" },
+  { key: "annotate", label: "Add comments" },
+  { key: "fillForms", label: "Fill forms" },
+  { key: "assemble", label: "Insert, delete and rotate pages" },
+];
+
+const ProtectPdf = () => {
+  const [loading, setLoading] = useState(false);
+  const [jobId, setJobId] = useState<string | null>(null);
+  const progress = useJobProgress(jobId);
+  const [inputPath, setInputPath] = useState<string | null>();
+  const [fileName, setFileName] = useState<string>("Unknown.pdf");
+  const [password, setPassword] = useState("");
+  const [ownerPassword, setOwnerPassword] = useState("");
+  const [encryption, setEncryption] = useState<Encryption>("aes256");
+  const [permissions, setPermissions] = useState<PdfPermissions>(allowAll);
+
+  const openFilePicker = async () => {
+    const path = await open({
+      multiple: false,
+      filters: [
+        {
+          name: "PDF Files",
+          extensions: ["pdf"],
+        },
+      ],
+    });
+    if (typeof path !== "string") {
+      return;
+    }
+    setInputPath(path);
+    setFileName(path.split(/[\\/]/).pop() ?? "Unknown.pdf");
+  };
+
+  const clearPdf = () => {
+    setInputPath(null);
+    setFileName("");
+    setPassword("");
+    setOwnerPassword("");
+    setPermissions(allowAll);
+  };
+
+  const protectPdf = async () => {
+    try {
+      const currentJob = crypto.randomUUID();
+      setJobId(currentJob);
+      setLoading(true);
+      const result = await invoke<PdfResult>("protect_pdf", {
+        inputPath,
+        password,
+        ownerPassword: ownerPassword || null,
+        permissions,
+        encryption,
+        jobId: currentJob,
+      });
+      message(pdfResultText(result));
+    } catch (error) {
+      setLoading(false);
+      if (!isCancelled(error)) {
+        message("Error: " + (isSliceError(error) ? error.message : error));
+      }
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const id = "protect-pdf";
+  const tool: Tool | undefined = toolData.tools.find((tool) => tool.id === id);
+  if (!tool) {
+    return <div className="p-4 text-red-500">Tool not found</div>;
+  }
+
+  return (
+    <div className="w-full h-full lg:px-50 lg:py-30 p-10">
+      {loading && (
+        <Loader
+          label="protecting"
+          progress={progress}
+          onCancel={() => invoke("cancel_job", { jobId })}
+        />
+      )}
+      <div className="w-full flex flex-col items-center justify-center gap-5">
+        <ToolCard
+          hide={true}
+          id={tool.id}
+          title={tool?.title}
+          description={tool.description}
+          icon={tool.icon}
+          color={tool.color}
+          link={tool.link}
+          tags={tool.tags}
+          mascot={tool.mascot}
+        />
+        <button
+          type="button"
+          onClick={openFilePicker}
+          className="px-8 py-4 w-full
+            bg-secondary text-secondary-foreground font-semibold rounded-xl hover:bg-secondary/80 transition-all duration-300 border border-border text-xl"
+        >
+          Upload a File
+        </button>
+
+        {inputPath == null ? (
+          <NoFilesYet />
+        ) : (
+          <>
+            <SelectedFileCard fileName={fileName} />
+
+            <div className="grid w-full grid-cols-1 gap-4 md:grid-cols-2">
+              <div className="space-y-2">
+                <label className="text-xs font-bold text-gray-400 uppercase">
+                  Password to open
+                </label>
+                <input
+                  type="password"
+                  value={password}
+                  placeholder="Leave empty to only restrict"
+                  onChange={(e) => setPassword(e.target.value)}
+                  className="w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500"
+                />
+              </div>
+              <div className="space-y-2">
+                <label className="text-xs font-bold text-gray-400 uppercase">
+                  Owner password
+                </label>
+                <input
+                  type="password"
+                  value={ownerPassword}
+                  placeholder="Unlocks the restrictions below"
+                  onChange={(e) => setOwnerPassword(e.target.value)}
+                  className="w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500"
+                />
+              </div>
+            </div>
+
+            <div className="w-full space-y-3">
+              <h3 className="font-bold text-gray-700">
+                Allowed without the owner password
+              </h3>
+              <div className="space-y-2">
+                <label className="text-xs font-bold text-gray-400 uppercase">
+                  Printing
+                </label>
+                <select
+                  value={permissions.print}
+                  onChange={(e) =>
+                    setPermissions({
+                      ...permissions,
+                      print: e.target.value as PrintPermission,
+                    })
+                  }
+                  className="w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500"
+                >
+                  <option value="highQuality">High quality</option>
+                  <option value="lowQuality">Low quality only</option>
+                  <option value="none">Not allowed</option>
+                </select>
+              </div>
+              {permissionOptions.map((option) => (
+                <label
+                  key={option.key}
+                  className="flex items-center gap-3 cursor-pointer"
+                >
+                  <input
+                    type="checkbox"
+                    checked={permissions[option.key]}
+                    onChange={(e) =>
+                      setPermissions({
+                        ...permissions,
+                        [option.key]: e.target.checked,
+                      })
+                    }
+                    className="accent-blue-600"
+                  />
+                  <span className="font-medium">{option.label}</span>
+                </label>
+              ))}
+            </div>
+
+            <div className="w-full space-y-3">
+              <h3 className="font-bold text-gray-700">Encryption</h3>
+              <label className="flex items-start gap-3 cursor-pointer">
+                <input
+                  type="radio"
+                  checked={encryption === "aes256"}
+                  onChange={() => setEncryption("aes256")}
+                  className="accent-blue-600 mt-1"
+                />
+                <div>
+                  <p className="font-medium">AES-256</p>
+                  <p className="text-xs text-gray-500">
+                    Strongest, PDF 2.0 readers
+                  </p>
+                </div>
+              </label>
+              <label className="flex items-start gap-3 cursor-pointer">
+                <input
+                  type="radio"
+                  checked={encryption === "aes128"}
+                  onChange={() => setEncryption("aes128")}
+                  className="accent-blue-600 mt-1"
+                />
+                <div>
+                  <p className="font-medium">AES-128</p>
+                  <p className="text-xs text-gray-500">
+                    For older PDF readers
+                  </p>
+                </div>
+              </label>
+            </div>
+
+            <button
+              disabled={!password && !ownerPassword}
+              type="button"
+              onClick={protectPdf}
+              className="px-8 py-4 w-full  disabled:opacity-30   text-xl
+            bg-blue-500/10  hover:text-white    text-primary
+            font-semibold rounded-xl hover:bg-primary transition-all duration-300 border border-border"
+            >
+              Protect
+            </button>
+            <button
+              onClick={clearPdf}
+              className="px-6 py-3  w-full  text-xl disabled:opacity-50  font-semibold rounded-xl  border border-red-300 cursor-pointer"
+            >
+              Clear
+            </button>
+          </>
+        )}
+      </div>
+    </div>
+  );
+};
+
+export default ProtectPdf;
@@ -5,6 +5,8 @@ import { invoke } from "@tauri-apps/api/core";
 import { ToolCard } from "../components/ToolCard";
 import toolData from "../data/tools.json";
 import Loader from "../components/Loading";
+import { useJobProgress } from "../hooks/useJobProgress";
+import { isCancelled, isSliceError } from "../types/errors";
 import { Tool } from "../types/tools";
 import { Upload } from "lucide-react";
 
@@ -12,6 +14,8 @@ const ResizeImage = () => {
   const [imagePath, setImagePath] = useState<string | null>(null);
   const [previewUrl, setPreviewUrl] = useState<string | null>(null);
   const [loading, setLoading] = useState(false);
+  const [jobId, setJobId] = useState<string | null>(null);
+  const progress = useJobProgress(jobId);
   const [dimensions, setDimensions] = useState({
     width: 0,
     height: 0,
@@ -48,6 +52,8 @@ const ResizeImage = () => {
 
   const handleResize = async () => {
     if (!imagePath) return;
+    const currentJob = crypto.randomUUID();
+    setJobId(currentJob);
     setLoading(true);
     try {
       await invoke("resize_image", {
@@ -55,10 +61,16 @@ const ResizeImage = () => {
         width: mode === "dimensions" ? dimensions.width : null,
         height: mode === "dimensions" ? dimensions.height : null,
         percentage: mode === "percentage" ? dimensions.percentage : null,
+        jobId: currentJob,
       });
       message("Image resized successfully!");
     } catch (error) {
-      message("Error resizing image: " + error);
+      if (!isCancelled(error)) {
+        message(
+          "Error resizing image: " +
+            (isSliceError(error) ? error.message : error),
+        );
+      }
     } finally {
       setLoading(false);
     }
@@ -68,7 +80,13 @@ const ResizeImage = () => {
 
   return (
     <div className="w-full h-full lg:px-50 lg:py-20 p-10">
-      {loading && <Loader label="Resizing Image..." />}
+      {loading && (
+        <Loader
+          label="Resizing Image..."
+          progress={progress}
+          onCancel={() => invoke("cancel_job", { jobId })}
+        />
+      )}
 
       <div className="flex flex-col items-center gap-8 ">
         <ToolCard hide={true} {...tool} />
@@ -6,10 +6,11 @@ import { invoke } from "@tauri-apps/api/core";
 import { useEffect, useRef, useState } from "react";
 import * as pdfJs from "pdfjs-dist";
 import workerSrc from "pdfjs-dist/build/pdf.worker.min.mjs?url";
-import { readFile } from "@tauri-apps/plugin-fs";
 import Loader from "../components/Loading";
 import NoFilesYet from "../components/NoFilesYet";
 import SelectedFileCard from "../components/SelectedPdfCard";
+import { usePdfOpener } from "../hooks/usePdfOpener";
+import { isCancelled, isSliceError } from "../types/errors";
 
 pdfJs.GlobalWorkerOptions.workerSrc = workerSrc;
 
@@ -19,6 +20,7 @@ type SourcePdfMap = Record<
     filename: string;
     pdf: pdfJs.PDFDocumentProxy;
     filepath: string;
+    password?: string;
   }
 >;
 type RotatablePage = {
@@ -32,6 +34,7 @@ const RotatePdfPages = () => {
   const [sourcePdfs, setSourcePdfs] = useState<SourcePdfMap>({});
   const [pages, setPages] = useState<RotatablePage[]>([]);
   const [fileName, setFileName] = useState<string>("Unknown.pdf");
+  const { openPdf, passwordPrompt } = usePdfOpener();
 
   const openFilePicker = async () => {
     if (Object.keys(sourcePdfs).length >= 1) {
@@ -42,17 +45,15 @@ const RotatePdfPages = () => {
     const newPages: RotatablePage[] = [];
     if (path === null) return;
     setFileName(path.split(/[\\/]/).pop() ?? "Unknown.pdf");
-    const bytes = await readFile(path);
-    let pdf = null;
+    let opened = null;
     try {
-      pdf = await pdfJs.getDocument({ data: bytes }).promise;
-    } catch (err: any) {
-      if (err.name === "PasswordException" && err.code === 1) {
-        message("PDF is password protected ! Use the unlocked version ");
-        return;
-      }
+      opened = await openPdf(path);
+    } catch (err) {
+      message(`Could not load ${path}. It might be corrupted.`);
+      return;
     }
-    if (pdf == null) return;
+    if (opened == null) return;
+    const { pdf, password } = opened;
     const fileName = path.split(/[\\/]/).pop() ?? "Unknown.pdf";
     const pdfId = crypto.randomUUID();
 
@@ -60,6 +61,7 @@ const RotatePdfPages = () => {
       filename: fileName,
       pdf,
       filepath: path,
+      password,
     };
     for (let i = 1; i <= pdf.numPages; i++) {
       newPages.push({
@@ -96,8 +98,10 @@ const RotatePdfPages = () => {
   const rotatePdf = async () => {
     setLoading(true);
     let filepath = "";
+    let password: string | undefined;
     Object.values(sourcePdfs).forEach((value) => {
       filepath = value.filepath;
+      password = value.password;
     });
 
     const processingPayload = pages
@@ -112,10 +116,14 @@ const RotatePdfPages = () => {
     try {
       const result = await invoke("rotate_pdf_pages", {
         instructions: processingPayload,
+        password,
       });
 
       message(String(result));
     } catch (error) {
+      if (!isCancelled(error)) {
+        message("Error: " + (isSliceError(error) ? error.message : error));
+      }
     } finally {
       setLoading(false);
     }
@@ -135,6 +143,7 @@ const RotatePdfPages = () => {
   return (
     <>
       <div className="w-full h-full lg:px-50 lg:py-30 p-10">
+        {passwordPrompt}
         {loading && <Loader label="merging" />}
         <div className="w-full flex flex-col items-center justify-center gap-5">
           <ToolCard
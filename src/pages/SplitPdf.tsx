@@ -0,0 +1,269 @@
+import { ToolCard } from "../components/ToolCard";
+import { Tool } from "../types/tools";
+import toolData from "../data/tools.json";
+import { message, open } from "@tauri-apps/plugin-dialog";
+import { invoke } from "@tauri-apps/api/core";
+import { useState } from "react";
+import Loader from "../components/Loading";
+import { useJobProgress } from "../hooks/useJobProgress";
+import { usePdfOpener } from "../hooks/usePdfOpener";
+import SelectedFileCard from "../components/SelectedPdfCard";
+import NoFilesYet from "../components/NoFilesYet";
+import { isCancelled, isSliceError } from "../types/errors";
+import { SplitMode } from "../types/pdf";
+
+type ModeName = SplitMode["mode"];
+
+const modeOptions: { mode: ModeName; label: string; hint: string }[] = [
+  { mode: "ranges", label: "By page ranges", hint: "One file per range" },
+  { mode: "every", label: "Every few pages", hint: "Files of equal length" },
+  { mode: "burst", label: "Every page", hint: "One file per page" },
+  {
+    mode: "bookmarks",
+    label: "By bookmarks",
+    hint: "One file per chapter in the outline",
+  },
+  {
+    mode: "maxSize",
+    label: "By file size",
+    hint: "Files small enough to attach to an email",
+  },
+];
+
+const SplitPdf = () => {
+  const [loading, setLoading] = useState(false);
+  const [jobId, setJobId] = useState<string | null>(null);
+  const progress = useJobProgress(jobId);
+  const [inputPath, setInputPath] = useState<string | null>();
+  const [fileName, setFileName] = useState<string>("Unknown.pdf");
+  const [password, setPassword] = useState<string>();
+  const [pageCount, setPageCount] = useState(0);
+  const [mode, setMode] = useState<ModeName>("ranges");
+  const [ranges, setRanges] = useState("");
+  const [pagesPerFile, setPagesPerFile] = useState(1);
+  const [megabytes, setMegabytes] = useState(10);
+  const [nameTemplate, setNameTemplate] = useState("{name}-{part}");
+  const { openPdf, passwordPrompt } = usePdfOpener();
+
+  const openFilePicker = async () => {
+    const path = await open({
+      multiple: false,
+      filters: [
+        {
+          name: "PDF Files",
+          extensions: ["pdf"],
+        },
+      ],
+    });
+    if (typeof path !== "string") {
+      return;
+    }
+    const opened = await openPdf(path).catch(() => null);
+    if (!opened) return;
+    setPageCount(opened.pdf.numPages);
+    opened.pdf.destroy();
+    setPassword(opened.password);
+    setInputPath(path);
+    setFileName(path.split(/[\\/]/).pop() ?? "Unknown.pdf");
+  };
+
+  const clearPdf = () => {
+    setInputPath(null);
+    setFileName("");
+    setPassword(undefined);
+    setPageCount(0);
+    setRanges("");
+  };
+
+  const splitMode = (): SplitMode => {
+    switch (mode) {
+      case "ranges":
+        return { mode, ranges };
+      case "every":
+        return { mode, pages: pagesPerFile };
+      case "burst":
+      case "bookmarks":
+        return { mode };
+      case "maxSize":
+        return { mode, megabytes };
+    }
+  };
+
+  const splitPdf = async () => {
+    try {
+      const currentJob = crypto.randomUUID();
+      setJobId(currentJob);
+      setLoading(true);
+      const result = await invoke<string>("split_pdf", {
+        inputPath,
+        password,
+        mode: splitMode(),
+        nameTemplate,
+        jobId: currentJob,
+      });
+      message(result);
+    } catch (error) {
+      setLoading(false);
+      if (!isCancelled(error)) {
+        message("Error: " + (isSliceError(error) ? error.message : error));
+      }
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const id = "split-pdf";
+  const tool: Tool | undefined = toolData.tools.find((tool) => tool.id === id);
+  if (!tool) {
+    return <div className="p-4 text-red-500">Tool not found</div>;
+  }
+
+  return (
+    <div className="w-full h-full lg:px-50 lg:py-30 p-10">
+      {passwordPrompt}
+      {loading && (
+        <Loader
+          label="splitting"
+          progress={progress}
+          onCancel={() => invoke("cancel_job", { jobId })}
+        />
+      )}
+      <div className="w-full flex flex-col items-center justify-center gap-5">
+        <ToolCard
+          hide={true}
+          id={tool.id}
+          title={tool?.title}
+          description={tool.description}
+          icon={tool.icon}
+          color={tool.color}
+          link={tool.link}
+          tags={tool.tags}
+          mascot={tool.mascot}
+        />
+        <button
+          type="button"
+          onClick={openFilePicker}
+          className="px-8 py-4 w-full
+            bg-secondary text-secondary-foreground font-semibold rounded-xl hover:bg-secondary/80 transition-all duration-300 border border-border text-xl"
+        >
+          Upload a File
+        </button>
+
+        {inputPath == null ? (
+          <NoFilesYet />
+        ) : (
+          <>
+            <SelectedFileCard fileName={fileName} />
+
+            <div className="w-full space-y-3">
+              <h3 className="font-bold text-gray-700">
+                Split {pageCount} pages
+              </h3>
+              {modeOptions.map((option) => (
+                <label
+                  key={option.mode}
+                  className="flex items-start gap-3 cursor-pointer"
+                >
+                  <input
+                    type="radio"
+                    checked={mode === option.mode}
+                    onChange={() => setMode(option.mode)}
+                    className="accent-blue-600 mt-1"
+                  />
+                  <div>
+                    <p className="font-medium">{option.label}</p>
+                    <p className="text-xs text-gray-500">{option.hint}</p>
+                  </div>
+                </label>
+              ))}
+            </div>
+
+            {mode === "ranges" && (
+              <div className="w-full space-y-2">
+                <label className="text-xs font-bold text-gray-400 uppercase">
+                  Page ranges
+                </label>
+                <input
+                  type="text"
+                  value={ranges}
+                  placeholder="e.g. 1-3,5,8-"
+                  onChange={(e) => setRanges(e.target.value)}
+                  className="w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500"
+                />
+              </div>
+            )}
+            {mode === "every" && (
+              <div className="w-full space-y-2">
+                <label className="text-xs font-bold text-gray-400 uppercase">
+                  Pages per file
+                </label>
+                <input
+                  type="number"
+                  min={1}
+                  max={pageCount || undefined}
+                  value={pagesPerFile}
+                  onChange={(e) => setPagesPerFile(Number(e.target.value))}
+                  className="w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500"
+                />
+              </div>
+            )}
+            {mode === "maxSize" && (
+              <div className="w-full space-y-2">
+                <label className="text-xs font-bold text-gray-400 uppercase">
+                  Maximum size per file (MB)
+                </label>
+                <input
+                  type="number"
+                  min={0.1}
+                  step={0.1}
+                  value={megabytes}
+                  onChange={(e) => setMegabytes(Number(e.target.value))}
+                  className="w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500"
+                />
+              </div>
+            )}
+
+            <div className="w-full space-y-2">
+              <label className="text-xs font-bold text-gray-400 uppercase">
+                File names
+              </label>
+              <input
+                type="text"
+                value={nameTemplate}
+                onChange={(e) => setNameTemplate(e.target.value)}
+                className="w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500"
+              />
+              <p className="text-xs text-gray-500">
+                Use {"{name}"}, {"{part}"}, {"{first}"}, {"{last}"} and{" "}
+                {"{title}"}
+              </p>
+            </div>
+
+            <button
+              disabled={
+                (mode === "ranges" && !ranges.trim()) ||
+                (mode === "every" && pagesPerFile < 1) ||
+                (mode === "maxSize" && !(megabytes > 0))
+              }
+              type="button"
+              onClick={splitPdf}
+              className="px-8 py-4 w-full  disabled:opacity-30   text-xl
+            bg-blue-500/10  hover:text-white    text-primary
+            font-semibold rounded-xl hover:bg-primary transition-all duration-300 border border-border"
+            >
+              Split
+            </button>
+            <button
+              onClick={clearPdf}
+              className="px-6 py-3  w-full  text-xl disabled:opacity-50  font-semibold rounded-xl  border border-red-300 cursor-pointer"
+            >
+              Clear
+            </button>
+          </>
+        )}
+      </div>
+    </div>
+  );
+};
+
+export default SplitPdf;
@@ -0,0 +1,146 @@
+import { ToolCard } from "../components/ToolCard";
+import { Tool } from "../types/tools";
+import toolData from "../data/tools.json";
+import { message, open } from "@tauri-apps/plugin-dialog";
+import { invoke } from "@tauri-apps/api/core";
+import { useState } from "react";
+import Loader from "../components/Loading";
+import { useJobProgress } from "../hooks/useJobProgress";
+import SelectedFileCard from "../components/SelectedPdfCard";
+import NoFilesYet from "../components/NoFilesYet";
+import { isCancelled, isSliceError } from "../types/errors";
+import { PdfResult, pdfResultText } from "../types/pdf";
+
+const UnlockPdf = () => {
+  const [loading, setLoading] = useState(false);
+  const [jobId, setJobId] = useState<string | null>(null);
+  const progress = useJobProgress(jobId);
+  const [inputPath, setInputPath] = useState<string | null>();
+  const [fileName, setFileName] = useState<string>("Unknown.pdf");
+  const [password, setPassword] = useState("");
+
+  const openFilePicker = async () => {
+    const path = await open({
+      multiple: false,
+      filters: [
+        {
+          name: "PDF Files",
+          extensions: ["pdf"],
+        },
+      ],
+    });
+    if (typeof path !== "string") {
+      return;
+    }
+    setInputPath(path);
+    setFileName(path.split(/[\\/]/).pop() ?? "Unknown.pdf");
+  };
+
+  const clearPdf = () => {
+    setInputPath(null);
+    setFileName("");
+    setPassword("");
+  };
+
+  const unlockPdf = async () => {
+    try {
+      const currentJob = crypto.randomUUID();
+      setJobId(currentJob);
+      setLoading(true);
+      const result = await invoke<PdfResult>("decrypt_pdf", {
+        inputPath,
+        password: password || null,
+        temp: false,
+        jobId: currentJob,
+      });
+      message(pdfResultText(result));
+    } catch (error) {
+      setLoading(false);
+      if (isSliceError(error) && error.code === "WRONG_PASSWORD") {
+        message("That password does not open this PDF");
+      } else if (!isCancelled(error)) {
+        message("Error: " + (isSliceError(error) ? error.message : error));
+      }
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const id = "unlock-pdf";
+  const tool: Tool | undefined = toolData.tools.find((tool) => tool.id === id);
+  if (!tool) {
+    return <div className="p-4 text-red-500">Tool not found</div>;
+  }
+
+  return (
+    <div className="w-full h-full lg:px-50 lg:py-30 p-10">
+      {loading && (
+        <Loader
+          label="unlocking"
+          progress={progress}
+          onCancel={() => invoke("cancel_job", { jobId })}
+        />
+      )}
+      <div className="w-full flex flex-col items-center justify-center gap-5">
+        <ToolCard
+          hide={true}
+          id={tool.id}
+          title={tool?.title}
+          description={tool.description}
+          icon={tool.icon}
+          color={tool.color}
+          link={tool.link}
+          tags={tool.tags}
+          mascot={tool.mascot}
+        />
+        <button
+          type="button"
+          onClick={openFilePicker}
+          className="px-8 py-4 w-full
+            bg-secondary text-secondary-foreground font-semibold rounded-xl hover:bg-secondary/80 transition-all duration-300 border border-border text-xl"
+        >
+          Upload a File
+        </button>
+
+        {inputPath == null ? (
+          <NoFilesYet />
+        ) : (
+          <>
+            <SelectedFileCard fileName={fileName} />
+
+            <div className="w-full space-y-2">
+              <label className="text-xs font-bold text-gray-400 uppercase">
+                Password
+              </label>
+              <input
+                type="password"
+                value={password}
+                placeholder="Leave empty if the PDF opens without one"
+                onChange={(e) => setPassword(e.target.value)}
+                className="w-full p-3 bg-gray-50 border border-gray-200 rounded-xl outline-none focus:border-blue-500"
+              />
+            </div>
+
+            <button
+              type="button"
+              onClick={unlockPdf}
+              className="px-8 py-4 w-full  disabled:opacity-30   text-xl
+            bg-blue-500/10  hover:text-white    text-primary
+            font-semibold rounded-xl hover:bg-primary transition-all duration-300 border border-border"
+            >
+              Unlock
+            </button>
+            <button
+              onClick={clearPdf}
+              className="px-6 py-3  w-full  text-xl disabled:opacity-50  font-semibold rounded-xl  border border-red-300 cursor-pointer"
+            >
+              Clear
+            </button>
+          </>
+        )}
+      </div>
+    </div>
+  );
+};
+
+export default UnlockPdf;
@@ -5,6 +5,8 @@ import { invoke } from "@tauri-apps/api/core";
 import { ToolCard } from "../components/ToolCard";
 import toolData from "../data/tools.json";
 import Loader from "../components/Loading";
+import { useJobProgress } from "../hooks/useJobProgress";
+import { isCancelled, isSliceError } from "../types/errors";
 import { Tool } from "../types/tools";
 import { Upload } from "lucide-react";
 
@@ -18,6 +20,8 @@ const CompressImage = () => {
   const [imagePath, setImagePath] = useState<string | null>(null);
   const [previewUrl, setPreviewUrl] = useState<string | null>(null);
   const [loading, setLoading] = useState(false);
+  const [jobId, setJobId] = useState<string | null>(null);
+  const progress = useJobProgress(jobId);
 
   const [originalSizeKb, setOriginalSizeKb] = useState<number>(0);
 
@@ -83,16 +87,23 @@ const CompressImage = () => {
       return;
     }
 
+    const currentJob = crypto.randomUUID();
+    setJobId(currentJob);
     setLoading(true);
     try {
       const result = await invoke<string>("compress_image", {
         inputPath: imagePath,
         targetSize: targetSizeKb,
         mode: compressionType,
+        jobId: currentJob,
       });
       message(result, { title: "Success" });
     } catch (err) {
-      message(String(err), { title: "Compression failed" });
+      if (!isCancelled(err)) {
+        message(isSliceError(err) ? err.message : String(err), {
+          title: "Compression failed",
+        });
+      }
     } finally {
       setLoading(false);
     }
@@ -102,7 +113,13 @@ const CompressImage = () => {
 
   return (
     <div className="w-full h-full lg:px-50 lg:py-20 p-10">
-      {loading && <Loader label="Compressing Image..." />}
+      {loading && (
+        <Loader
+          label="Compressing Image..."
+          progress={progress}
+          onCancel={() => invoke("cancel_job", { jobId })}
+        />
+      )}
 
       <div className="flex flex-col items-center gap-8">
         <ToolCard hide {...tool} />
@@ -0,0 +1,30 @@
+export type SliceErrorCode =
+  | "CANCELLED"
+  | "INVALID_INPUT"
+  | "ENCRYPTED"
+  | "WRONG_PASSWORD"
+  | "PAGE_OUT_OF_RANGE"
+  | "IO"
+  | "PARSE_FAILURE"
+  | "SIDECAR_MISSING"
+  | "INTERNAL";
+
+// Shape of every error rejected by a Tauri command.
+export interface SliceError {
+  code: SliceErrorCode;
+  message: string;
+  context: Record<string, unknown> | null;
+}
+
+export function isSliceError(error: unknown): error is SliceError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    "code" in error &&
+    "message" in error
+  );
+}
+
+export function isCancelled(error: unknown): boolean {
+  return isSliceError(error) && error.code === "CANCELLED";
+}
@@ -0,0 +1,10 @@
+export type JobStep = "load" | "page" | "image" | "part" | "save";
+
+// Payload of the `job://progress` event emitted by long-running commands.
+export interface ProgressEvent {
+  jobId: string;
+  step: JobStep;
+  done: number;
+  total: number;
+  current: string | null;
+}
@@ -0,0 +1,86 @@
+// Result of `protect_pdf` and `decrypt_pdf`.
+export type PdfResult =
+  | { Message: { message: string } }
+  | { TempPath: { path: string } };
+
+export function pdfResultText(result: PdfResult): string {
+  return "Message" in result ? result.Message.message : result.TempPath.path;
+}
+
+export type Encryption = "aes256" | "aes128";
+
+export type PrintPermission = "none" | "lowQuality" | "highQuality";
+
+export interface PdfPermissions {
+  print: PrintPermission;
+  modify: boolean;
+  copy: boolean;
+  annotate: boolean;
+  fillForms: boolean;
+  assemble: boolean;
+}
+
+// How `split_pdf` cuts a document into files.
+export type SplitMode =
+  | { mode: "ranges"; ranges: string }
+  | { mode: "every"; pages: number }
+  | { mode: "burst" }
+  | { mode: "bookmarks" }
+  | { mode: "maxSize"; megabytes: number };
+
+// Size of a generated page, in points (1/72 inch).
+export interface PageSize {
+  width: number;
+  height: number;
+}
+
+// One page of a document built by `merge_all`. Files are named by their id in the
+// file map.
+export type PageSource =
+  | { kind: "pdf"; fileId: string; pageNumber: number; rotation?: number }
+  | { kind: "image"; fileId: string }
+  | { kind: "blank"; size: PageSize }
+  | { kind: "text"; text: string; size?: PageSize };
+
+// Paper of the pages made from images. Custom sizes are in points.
+export type PaperSize =
+  | { kind: "a4" }
+  | { kind: "letter" }
+  | { kind: "legal" }
+  | { kind: "a3" }
+  | { kind: "custom"; width: number; height: number }
+  | { kind: "sameAsImage" };
+
+export type Orientation = "auto" | "portrait" | "landscape";
+
+export type FitMode = "fit" | "fill" | "stretch";
+
+// Layout of the pages made from images. The margin is in points.
+export interface ImagePageOptions {
+  paper: PaperSize;
+  orientation: Orientation;
+  margin: number;
+  fit: FitMode;
+  maxDpi: number;
+}
+
+// Options of `merge_pdf` and `merge_all`.
+export interface MergeOptions {
+  bookmarkSources: boolean;
+  imagePages?: ImagePageOptions;
+}
+
+// Custom settings of `compress_pdf`. Quality goes from 1 to 100.
+export interface CompressOptions {
+  maxDpi: number;
+  quality: number;
+}
+
+// How hard `compress_pdf` compresses. Target sizes are in bytes.
+export type CompressMode =
+  | { kind: "screen" }
+  | { kind: "ebook" }
+  | { kind: "print" }
+  | { kind: "lossless" }
+  | ({ kind: "custom" } & CompressOptions)
+  | { kind: "targetSize"; bytes: number };